@@ -1,5 +1,37 @@
 pub const CONTRACT_NAME: &str = "crates.io:cw-lootboxes";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-pub const TOTAL_POOL_SIZE: u64 = 1000000;
 
 pub const MAX_HOUSE_FEE: u64 = 5000; // this is 50%, e.g. 10000 is 100%
+
+// Keepers can get at most 10% of the pool for a call.
+pub const MAX_KEEPER_BOUNTY_PERCENT: u64 = 10;
+
+// Version of the winning ticket derivation in util::winning_ticket, stored with every draw.
+pub const DRAW_VERSION: u32 = 1;
+
+// Used when no draw deadline is given on instantiation, 3 days.
+pub const DEFAULT_DRAW_DEADLINE_SECONDS: u64 = 259_200;
+
+// drand rounds skipped past the next one when none is configured, a minute on mainnet.
+pub const DEFAULT_DRAND_SAFETY_MARGIN: u64 = 2;
+
+// Used when no refund window is given on instantiation, 30 days.
+pub const DEFAULT_REFUND_WINDOW_SECONDS: u64 = 2_592_000;
+
+pub const IBC_APP_VERSION: &str = "sphinx-randomness-1";
+
+// Page sizes of the paginated queries
+pub const DEFAULT_QUERY_LIMIT: u32 = 10;
+pub const MAX_QUERY_LIMIT: u32 = 30;
+
+pub const INSTANTIATE_TICKET_TOKEN_REPLY_ID: u64 = 1;
+pub const HOOK_REPLY_ID: u64 = 2;
+
+// Every hook is notified in the same transaction, so their number and gas are bounded.
+pub const MAX_HOOKS: usize = 10;
+pub const DEFAULT_HOOK_GAS_LIMIT: u64 = 300_000;
+// cw20-base caps enumeration queries at 30 accounts per page.
+pub const TICKET_SNAPSHOT_PAGE_LIMIT: u32 = 30;
+// Passes over the cw20 holders before the snapshot gives up and the round can be refunded, so
+// tickets moved around behind every pass can't hold the round open forever.
+pub const MAX_TICKET_SNAPSHOT_PASSES: u32 = 3;
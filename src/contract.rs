@@ -1,24 +1,55 @@
-use std::ops::{Div, Mul, Range};
-
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Response,
-    StdResult, SubMsg, Uint128,
+    to_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, Event, HexBinary,
+    MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128, Uint64,
+    WasmMsg,
 };
 use cw2::set_contract_version;
-use cw_utils::{must_pay, Expiration};
-use rand::{Rng, SeedableRng};
+use cw20::{
+    AllAccountsResponse, BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse,
+    TokenInfoResponse,
+};
+use cw_storage_plus::Bound;
+use cw_utils::{must_pay, parse_reply_instantiate_data, Duration, Expiration};
 
-use crate::constants::{CONTRACT_NAME, CONTRACT_VERSION, TOTAL_POOL_SIZE};
+use crate::constants::{
+    CONTRACT_NAME, CONTRACT_VERSION, DEFAULT_DRAW_DEADLINE_SECONDS, DEFAULT_HOOK_GAS_LIMIT,
+    DEFAULT_QUERY_LIMIT, DEFAULT_REFUND_WINDOW_SECONDS, DRAW_VERSION, HOOK_REPLY_ID,
+    INSTANTIATE_TICKET_TOKEN_REPLY_ID, MAX_HOOKS, MAX_QUERY_LIMIT, MAX_TICKET_SNAPSHOT_PASSES,
+    TICKET_SNAPSHOT_PAGE_LIMIT,
+};
 use crate::error::ContractError;
-use crate::helpers::get_player_ranges;
-use crate::models::PlayerRanges;
+use crate::events::{
+    claim_event, config_change_event, draw_event, draw_expired_event, keeper_bounty_event,
+    purchase_event, refund_event, round_started_event, sales_closed_event, ticket_snapshot_event,
+    ticket_transfer_event,
+};
+use crate::ibc::{execute_request_randomness, randomness_request_msg};
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, LotteryStateResponse, QueryMsg, TicketResponse,
+    BondResponse, ConfigResponse, ExecuteMsg, FeeCut, HooksResponse, InstantiateMsg,
+    LotteryHookMsg, LotteryStateResponse, OddsResponse, OperatorMessageResponse, OracleCallback,
+    OracleExecuteMsg, ParticipantEntropyResponse, PayoutPreviewResponse, Phase,
+    PlayerHistoryResponse, PlayerRound, PlayerTickets, PlayersResponse, PurchaseResponse,
+    PurchasesResponse, QueryMsg, RandomnessRequestResponse, SimulatePurchaseResponse,
+    StatsResponse, StatusResponse, TicketResponse, TopWinnersResponse, VerifyDrawResponse,
+};
+use crate::state::{
+    purchases, ticket_ranges, winnings, Bond, BondStatus, Config, DrawRecord, Hook, KeeperBounty,
+    LotteryState, ParticipantEntropy, Purchase, RandomnessRequestStatus, RandomnessSource,
+    RefundPool, TicketMode, TicketRange, TicketSnapshot, Winnings, BONDS, CONFIG, DRAND_ROUND,
+    DRAWS, DRAW_DEADLINE, HOOKS, KEEPER_BOUNTIES, LOTTERY_STATE, PARTICIPANT_ENTROPY,
+    PARTICIPATIONS, PLAYERS, PURCHASE_COUNT, RANDOMNESS_CHANNEL, RANDOMNESS_REQUEST, REFUNDS,
+    REFUND_POOLS, RESERVED_REFUNDS, ROUND, SEED_COMMITMENT, SNAPSHOT_BALANCES, STATS, TICKETS_SOLD,
+    TICKET_LEDGER_HASH, TICKET_SNAPSHOT, TICKET_TOKEN, TOTAL_TICKETS,
+};
+use crate::util::{
+    accumulate_entropy, drand_round_time, is_admin, ledger_hash, mix_entropy, next_drand_round,
+    operator_message, oracle_job_id, round_seed_commitment, seed_entropy, split_prize_pool,
+    ticket_share, validate_bond, validate_house_fee, validate_keeper_bounty,
+    validate_randomness_source, verify_drand_beacon, verify_operator_signature, verify_seed_reveal,
+    winning_ticket,
 };
-use crate::state::{Config, LotteryState, CONFIG, LOTTERY_STATE, PLAYERS};
-use crate::util::{is_admin, validate_house_fee};
 
 /*
 Each individual contract owner will be able to creat their own lottery.
@@ -42,25 +73,145 @@ pub fn instantiate(
     let admin_addr = deps.api.addr_validate(&msg.admin)?;
     let house_fee = validate_house_fee(msg.house_fee)?;
     let house_fee_percentage = Decimal::percent(house_fee);
+    let bond = validate_bond(msg.bond, &msg.ticket_cost)?;
 
     let config = Config {
         admin: admin_addr,
         house_fee: house_fee_percentage,
         ticket_unit_cost: msg.ticket_cost,
+        ticket_mode: msg.ticket_mode.unwrap_or(TicketMode::Native {}),
+        lottery_duration: msg.lottery_duration,
+        draw_deadline: msg
+            .draw_deadline
+            .unwrap_or(Duration::Time(DEFAULT_DRAW_DEADLINE_SECONDS)),
+        refund_window: msg
+            .refund_window
+            .unwrap_or(Duration::Time(DEFAULT_REFUND_WINDOW_SECONDS)),
+        bond,
+        keeper_bounty: validate_keeper_bounty(msg.keeper_bounty)?,
+        randomness: validate_randomness_source(
+            deps.api,
+            msg.randomness.unwrap_or(RandomnessSource::CommitReveal {}),
+        )?,
     };
 
     CONFIG.save(deps.storage, &config)?;
 
-    LOTTERY_STATE.save(
-        deps.storage,
-        &LotteryState::OPEN {
-            expiration: msg.lottery_duration.after(&env.block),
-        },
-    )?;
+    let seed_commitment = round_seed_commitment(&config, msg.seed_commitment)?;
+    let (round_started_event, ticket_token_msg) =
+        open_round(deps.storage, &env, &config, 1, seed_commitment)?;
+    post_bond(deps.storage, &info, &config, 1)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("owner", info.sender))
+        .add_attribute("owner", info.sender)
+        .add_event(config_change_event(1, &config)?)
+        .add_event(round_started_event)
+        .add_submessages(ticket_token_msg))
+}
+
+// Opens ticket sales for a round. In cw20 mode every round gets its own ticket token,
+// the returned submessage instantiates it and its address is stored on reply. The bond
+// in the returned event is posted by the caller.
+fn open_round(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    round: u64,
+    seed_commitment: Option<HexBinary>,
+) -> Result<(Event, Option<SubMsg>), ContractError> {
+    ROUND.save(storage, &round)?;
+    match seed_commitment {
+        Some(seed_commitment) => SEED_COMMITMENT.save(storage, &seed_commitment)?,
+        None => SEED_COMMITMENT.remove(storage),
+    }
+    DRAND_ROUND.remove(storage);
+    TICKET_SNAPSHOT.remove(storage);
+    DRAW_DEADLINE.remove(storage);
+    RANDOMNESS_REQUEST.remove(storage);
+    PARTICIPANT_ENTROPY.remove(storage);
+    TICKET_LEDGER_HASH.remove(storage);
+    let expiration = config.lottery_duration.after(&env.block);
+    LOTTERY_STATE.save(storage, &LotteryState::OPEN { expiration })?;
+    let event = round_started_event(round, &expiration, config.bond.as_ref());
+
+    match &config.ticket_mode {
+        TicketMode::Native {} => Ok((event, None)),
+        TicketMode::Cw20 {
+            code_id,
+            name,
+            symbol,
+        } => {
+            let instantiate_token_msg = WasmMsg::Instantiate {
+                admin: Some(env.contract.address.to_string()),
+                code_id: *code_id,
+                msg: to_binary(&cw20_base::msg::InstantiateMsg {
+                    name: name.clone(),
+                    symbol: symbol.clone(),
+                    decimals: 0,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: env.contract.address.to_string(),
+                        cap: None,
+                    }),
+                    marketing: None,
+                })?,
+                funds: vec![],
+                label: format!("{} round {}", name, round),
+            };
+            Ok((
+                event,
+                Some(SubMsg::reply_on_success(
+                    instantiate_token_msg,
+                    INSTANTIATE_TICKET_TOKEN_REPLY_ID,
+                )),
+            ))
+        }
+    }
+}
+
+// The admin's bond for a round is sent along with the message opening it.
+fn post_bond(
+    storage: &mut dyn Storage,
+    info: &MessageInfo,
+    config: &Config,
+    round: u64,
+) -> Result<(), ContractError> {
+    if let Some(bond) = &config.bond {
+        if must_pay(info, &bond.denom)? != bond.amount {
+            return Err(ContractError::BondIncorrectAmount {
+                bond: bond.to_string(),
+            });
+        }
+        BONDS.save(
+            storage,
+            round,
+            &Bond {
+                amount: bond.clone(),
+                status: BondStatus::Posted {},
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_TICKET_TOKEN_REPLY_ID => {
+            let res = parse_reply_instantiate_data(msg)?;
+            let ticket_token = deps.api.addr_validate(&res.contract_address)?;
+            TICKET_TOKEN.save(deps.storage, &ticket_token)?;
+            Ok(Response::new()
+                .add_attribute("method", "reply_instantiate_ticket_token")
+                .add_attribute("ticket_token", ticket_token))
+        }
+        // A failing hook only reverts its own notification, the lottery carries on.
+        HOOK_REPLY_ID => Ok(Response::new()
+            .add_attribute("method", "reply_hook_failed")
+            .add_attribute("error", msg.result.into_result().err().unwrap_or_default())),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
 }
 
 /*
@@ -80,9 +231,36 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::BuyTicket { num_tickets } => execute_buy_ticket(deps, env, info, num_tickets),
-        ExecuteMsg::ExecuteLottery { seed } => execute_lottery(deps, env, info, seed),
+        ExecuteMsg::BuyTicket {
+            num_tickets,
+            recipient,
+            entropy,
+        } => execute_buy_ticket(deps, env, info, num_tickets, recipient, entropy),
+        ExecuteMsg::TransferTickets { recipient, amount } => {
+            execute_transfer_tickets(deps, env, info, recipient, amount)
+        }
+        ExecuteMsg::ExecuteLottery { seed, salt } => execute_lottery(deps, env, info, seed, salt),
         ExecuteMsg::ClaimTokens {} => execute_claim(deps, env, info),
+        ExecuteMsg::CloseSales {} => execute_close_sales(deps, env, info),
+        ExecuteMsg::StartRound { seed_commitment } => {
+            execute_start_round(deps, env, info, seed_commitment)
+        }
+        ExecuteMsg::SubmitDrandBeacon {
+            signature,
+            previous_signature,
+        } => execute_submit_drand_beacon(deps, env, info, signature, previous_signature),
+        ExecuteMsg::RequestRandomness {} => execute_request_randomness(deps, env),
+        ExecuteMsg::SubmitOperatorSignature { signature } => {
+            execute_submit_operator_signature(deps, env, info, signature)
+        }
+        ExecuteMsg::NoisReceive { callback } => execute_oracle_callback(deps, env, info, callback),
+        ExecuteMsg::ExpireDraw {} => execute_expire_draw(deps, env),
+        ExecuteMsg::ClaimRefund { round } => execute_claim_refund(deps, info, round),
+        ExecuteMsg::SetRandomnessSource { randomness } => {
+            execute_set_randomness_source(deps, info, randomness)
+        }
+        ExecuteMsg::AddHook { addr, gas_limit } => execute_add_hook(deps, info, addr, gas_limit),
+        ExecuteMsg::RemoveHook { addr } => execute_remove_hook(deps, info, addr),
     }
 }
 
@@ -91,14 +269,28 @@ fn execute_buy_ticket(
     _env: Env,
     info: MessageInfo,
     bought_tickets: u64,
+    recipient: Option<String>,
+    entropy: Option<HexBinary>,
 ) -> Result<Response, ContractError> {
+    // Tickets can be bought as a gift, by default they go to the buyer.
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
     let lottery_state = LOTTERY_STATE.load(deps.storage)?;
     match lottery_state {
-        LotteryState::OPEN { expiration } => {
-            handle_open_lottery(deps, &_env, &info, bought_tickets, expiration)
-        }
+        LotteryState::OPEN { expiration } => handle_open_lottery(
+            deps,
+            &_env,
+            &info,
+            &recipient,
+            bought_tickets,
+            entropy,
+            expiration,
+        ),
         LotteryState::CHOOSING {} => Err(ContractError::TicketBuyingNotAvailable {}),
         LotteryState::CLOSED { .. } => Err(ContractError::TicketBuyingNotAvailable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::TicketBuyingNotAvailable {}),
     }
 }
 
@@ -106,7 +298,9 @@ fn handle_open_lottery(
     deps: DepsMut,
     env: &Env,
     info: &MessageInfo,
+    recipient: &Addr,
     bought_tickets: u64,
+    entropy: Option<HexBinary>,
     expiration: Expiration,
 ) -> Result<Response, ContractError> {
     // function goal is to check to see if the lottery itself
@@ -122,335 +316,3483 @@ fn handle_open_lottery(
             .checked_mul(Uint128::new(u128::from(bought_tickets)))?;
         let amount_received_future = must_pay(info, &ticket_cost.denom)?;
         if amount_received_future == total_cost {
-            update_player(deps, info, bought_tickets)?;
-            Ok(Response::new())
+            if let Some(entropy) = entropy {
+                contribute_entropy(deps.storage, &info.sender, &entropy)?;
+            }
+            let round = ROUND.load(deps.storage)?;
+            record_participation(
+                deps.storage,
+                &info.sender,
+                round,
+                bought_tickets,
+                total_cost,
+            )?;
+            let paid = Coin {
+                denom: ticket_cost.denom.clone(),
+                amount: total_cost,
+            };
+            let purchase = record_purchase(
+                deps.storage,
+                env,
+                round,
+                &info.sender,
+                recipient,
+                bought_tickets,
+                paid,
+            )?;
+            let hook_msgs = hook_msgs(
+                deps.storage,
+                LotteryHookMsg::TicketsPurchased {
+                    round,
+                    purchase_id: purchase.id,
+                    buyer: purchase.buyer.clone(),
+                    recipient: purchase.recipient.clone(),
+                    tickets: purchase.tickets,
+                },
+            )?;
+            match config.ticket_mode {
+                TicketMode::Native {} => {
+                    let ticket_start =
+                        append_ticket_range(deps.storage, round, recipient, bought_tickets)?;
+                    add_player_tickets(deps, recipient, bought_tickets)?;
+                    Ok(Response::new()
+                        .add_submessages(hook_msgs)
+                        .add_event(purchase_event(&purchase, Some(ticket_start))))
+                }
+                TicketMode::Cw20 { .. } => {
+                    let mint_tickets_msg = WasmMsg::Execute {
+                        contract_addr: TICKET_TOKEN.load(deps.storage)?.to_string(),
+                        msg: to_binary(&Cw20ExecuteMsg::Mint {
+                            recipient: recipient.to_string(),
+                            amount: Uint128::from(bought_tickets),
+                        })?,
+                        funds: vec![],
+                    };
+                    // The tickets get their range once sales close, see ticket_snapshot.
+                    Ok(Response::new()
+                        .add_message(mint_tickets_msg)
+                        .add_submessages(hook_msgs)
+                        .add_event(purchase_event(&purchase, None)))
+                }
+            }
         } else {
             Err(ContractError::TicketBuyingIncorrectAmount {})
         }
     } else {
-        // Lottery is expired, therefore go ahead and update the state of the contract
-        // to next phase.
-        LOTTERY_STATE.save(deps.storage, &LotteryState::CHOOSING {})?;
-        Ok(Response::new())
+        // Sales are over even though nobody closed them yet. Erroring sends the funds back,
+        // CloseSales moves the round on.
+        Err(ContractError::TicketBuyingNotAvailable {})
     }
 }
 
-fn update_player(deps: DepsMut, info: &MessageInfo, bought_tickets: u64) -> StdResult<()> {
-    let some_player_info = PLAYERS.may_load(deps.storage, info.sender.clone())?;
-    match some_player_info {
-        None => PLAYERS.save(deps.storage, info.sender.clone(), &bought_tickets),
-        Some(previous_ticket_count) => PLAYERS.save(
-            deps.storage,
-            info.sender.clone(),
-            &(bought_tickets + previous_ticket_count),
-        ),
-    }?;
-
-    Result::Ok(())
+fn contribute_entropy(
+    storage: &mut dyn Storage,
+    contributor: &Addr,
+    entropy: &[u8],
+) -> StdResult<()> {
+    let participant_entropy = PARTICIPANT_ENTROPY.may_load(storage)?;
+    let accumulator = participant_entropy
+        .as_ref()
+        .map(|participant_entropy| participant_entropy.accumulator.as_slice());
+    PARTICIPANT_ENTROPY.save(
+        storage,
+        &ParticipantEntropy {
+            accumulator: accumulate_entropy(accumulator, contributor, entropy),
+            contributions: participant_entropy
+                .map_or(0, |participant_entropy| participant_entropy.contributions)
+                + 1,
+        },
+    )
 }
 
-fn execute_lottery(
-    deps: DepsMut,
-    _env: Env,
+fn execute_close_sales(
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    seed: u64,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
-    match lottery_state {
-        LotteryState::CHOOSING {} => {
-            is_admin(info.sender, config)?;
-            let winner = choose_winner(&deps, seed)?;
-            LOTTERY_STATE.save(
-                deps.storage,
-                &LotteryState::CLOSED {
-                    winner,
-                    claimed: false,
-                },
-            )?;
-            Ok(Response::new())
-        }
-        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
-        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
-    }
-}
-
-fn execute_claim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let lottery_state = LOTTERY_STATE.load(deps.storage)?;
     match lottery_state {
-        LotteryState::CLOSED { winner, claimed } => {
-            handle_lottery_claim(deps, &env, info, winner, claimed)
+        LotteryState::OPEN { expiration } => {
+            if !expiration.is_expired(&env.block) {
+                return Err(ContractError::TicketSalesStillOpen {});
+            }
+            let response =
+                close_sales(deps.branch(), &env, &info)?.add_attribute("method", "close_sales");
+            // Keepers are paid for the call that closes the round, not for every snapshot page.
+            // The funds sent along only pay the oracle fee of that call, the others send them back.
+            match LOTTERY_STATE.load(deps.storage)? {
+                LotteryState::CHOOSING {} => pay_keeper_bounty(deps, &info, response),
+                _ if info.funds.is_empty() => Ok(response),
+                _ => Ok(response.add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: info.funds,
+                })),
+            }
         }
-        LotteryState::CHOOSING {} => Err(ContractError::LotteryNotClaimable {}),
-        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotClaimable {}),
+        LotteryState::CHOOSING {} => Err(ContractError::TicketBuyingNotAvailable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::TicketBuyingNotAvailable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::TicketBuyingNotAvailable {}),
     }
 }
 
-fn handle_lottery_claim(
-    deps: DepsMut,
+fn close_sales(
+    mut deps: DepsMut,
     env: &Env,
-    info: MessageInfo,
-    winner: Addr,
-    claimed: bool,
+    info: &MessageInfo,
 ) -> Result<Response, ContractError> {
-    if !claimed {
-        if info.sender == winner {
-            // send contract funds, and update lottery state to "closed and claimed"
-            LOTTERY_STATE.save(
-                deps.storage,
-                &LotteryState::CLOSED {
-                    winner,
-                    claimed: true,
-                },
-            )?;
-
-            let config = CONFIG.load(deps.storage)?;
-            let admin = config.admin;
-            let house_fee = config.house_fee;
-            let ticket_cost = config.ticket_unit_cost;
-
-            let lottery_pool = deps
-                .querier
-                .query_balance(&env.contract.address, ticket_cost.denom.clone())?;
-
-            let amount_to_pay_in_fees = lottery_pool.amount * house_fee / Uint128::from(100u128);
-            let amount_to_pay_out_to_winner = lottery_pool.amount - amount_to_pay_in_fees;
-
-            let disperse_reward_msg = SubMsg::new(BankMsg::Send {
-                to_address: String::from(info.sender),
-                amount: vec![Coin {
-                    denom: ticket_cost.denom.clone(),
-                    amount: amount_to_pay_out_to_winner,
-                }],
-            });
+    let config = CONFIG.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let mut response = Response::new();
+    if let TicketMode::Cw20 { .. } = config.ticket_mode {
+        // The snapshot gets as long as a draw to finish, counted from the first call.
+        if DRAW_DEADLINE.may_load(deps.storage)?.is_none() {
+            DRAW_DEADLINE.save(deps.storage, &config.draw_deadline.after(&env.block))?;
+        }
+        let (events, snapshotted) = snapshot_ticket_token(&mut deps)?;
+        response = response.add_events(events);
+        // Sales are over, but the round only moves on once every holder was snapshotted.
+        if !snapshotted {
+            let status = match TICKET_SNAPSHOT.may_load(deps.storage)? {
+                Some(TicketSnapshot::Failed {}) => "failed",
+                _ => "in_progress",
+            };
+            return Ok(response.add_attribute("ticket_snapshot", status));
+        }
+    }
+    let drand_round = match &config.randomness {
+        RandomnessSource::Drand(drand) => {
+            let drand_round = next_drand_round(drand, env.block.time);
+            DRAND_ROUND.save(deps.storage, &drand_round)?;
+            Some(drand_round)
+        }
+        _ => None,
+    };
+    let draw_deadline = config.draw_deadline.after(&env.block);
+    DRAW_DEADLINE.save(deps.storage, &draw_deadline)?;
+    LOTTERY_STATE.save(deps.storage, &LotteryState::CHOOSING {})?;
+    let total_tickets = TOTAL_TICKETS
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    response = response.add_event(sales_closed_event(
+        round,
+        total_tickets,
+        &draw_deadline,
+        drand_round,
+    ));
 
-            let disperse_fee_msg = SubMsg::new(BankMsg::Send {
-                to_address: String::from(admin),
-                amount: vec![Coin {
-                    denom: ticket_cost.denom,
-                    amount: amount_to_pay_in_fees,
-                }],
+    // Without an open channel the request is sent through RequestRandomness later on.
+    match &config.randomness {
+        RandomnessSource::Ibc(ibc_randomness) => {
+            if let Some(request_msg) = randomness_request_msg(deps.storage, env, ibc_randomness)? {
+                response = response.add_message(request_msg);
+            }
+        }
+        RandomnessSource::Oracle(oracle) => {
+            // The caller pays the fee, the pool only ever pays the winner and the fees on it.
+            if let Some(fee) = &oracle.fee {
+                if must_pay(info, &fee.denom).ok() != Some(fee.amount) {
+                    return Err(ContractError::OracleFeeNotPaid {
+                        fee: fee.to_string(),
+                    });
+                }
+            }
+            response = response.add_message(WasmMsg::Execute {
+                contract_addr: oracle.contract.to_string(),
+                msg: to_binary(&OracleExecuteMsg::GetNextRandomness {
+                    job_id: oracle_job_id(round),
+                })?,
+                funds: oracle.fee.clone().into_iter().collect(),
             });
-
-            let mut response: Response = Default::default();
-
-            response.messages = vec![disperse_reward_msg, disperse_fee_msg];
-
-            Ok(response)
-        } else {
-            Err(ContractError::LotteryNotClaimedByCorrectUser {})
         }
-    } else {
-        Err(ContractError::LotteryAlreadyClaimed {})
+        _ => {}
     }
+    Ok(response.add_submessages(hook_msgs(
+        deps.storage,
+        LotteryHookMsg::SalesClosed {
+            round,
+            total_tickets,
+        },
+    )?))
 }
 
-fn choose_winner(deps: &DepsMut, seed: u64) -> Result<Addr, ContractError> {
-    let mut rng: rand::rngs::StdRng = SeedableRng::seed_from_u64(seed);
-    let total_tickets = get_num_tickets(deps);
-    let winner_ticket = rng.gen_range(Range {
-        start: 0,
-        end: total_tickets,
-    });
-    let player_ranges = create_player_ranges(deps, total_tickets);
+// cw20 tickets are snapshotted a page of holders at a time, over as many CloseSales calls as it
+// takes. Balances are only turned into ticket ranges once a pass over every holder adds up to
+// the token's supply: tickets moved behind the pass would be counted twice or not at all, so
+// the pass starts over instead. After MAX_TICKET_SNAPSHOT_PASSES passes the snapshot fails and
+// the round is refunded through ExpireDraw. Returns the snapshot events and whether it's done.
+fn snapshot_ticket_token(deps: &mut DepsMut) -> Result<(Vec<Event>, bool), ContractError> {
+    let snapshot = TICKET_SNAPSHOT
+        .may_load(deps.storage)?
+        .unwrap_or(TicketSnapshot::Reading {
+            pass: 0,
+            start_after: None,
+            counted: Uint128::zero(),
+        });
+    match snapshot {
+        TicketSnapshot::Reading {
+            pass,
+            start_after,
+            counted,
+        } => match read_ticket_balances(deps, pass, start_after, counted)? {
+            // Assigning a page right away closes rounds with few holders in a single call.
+            true => assign_ticket_ranges(deps, pass, None),
+            false => Ok((vec![], false)),
+        },
+        TicketSnapshot::Assigning { pass, start_after } => {
+            assign_ticket_ranges(deps, pass, start_after)
+        }
+        TicketSnapshot::Failed {} => Err(ContractError::TicketSnapshotFailed {}),
+    }
+}
 
-    let mut addr = None;
-    for player_range in player_ranges.ranges {
-        if winner_ticket <= player_range.end_range && winner_ticket >= player_range.start_range {
-            addr = Some(player_range.player_addr)
+// Reads a page of holders' balances, returns whether the pass is done and adds up.
+fn read_ticket_balances(
+    deps: &mut DepsMut,
+    pass: u32,
+    start_after: Option<String>,
+    mut counted: Uint128,
+) -> Result<bool, ContractError> {
+    let ticket_token = TICKET_TOKEN.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let page: AllAccountsResponse = deps.querier.query_wasm_smart(
+        &ticket_token,
+        &Cw20QueryMsg::AllAccounts {
+            start_after,
+            limit: Some(TICKET_SNAPSHOT_PAGE_LIMIT),
+        },
+    )?;
+    for account in &page.accounts {
+        let balance: BalanceResponse = deps.querier.query_wasm_smart(
+            &ticket_token,
+            &Cw20QueryMsg::Balance {
+                address: account.clone(),
+            },
+        )?;
+        if !balance.balance.is_zero() {
+            let holder = deps.api.addr_validate(account)?;
+            let tickets = Uint64::try_from(balance.balance)?.u64();
+            SNAPSHOT_BALANCES.save(deps.storage, (round, holder), &(pass, tickets))?;
+            counted = counted.checked_add(balance.balance)?;
         }
     }
-    match addr {
-        None => Err(ContractError::WinnerNotPossibleToFind {}),
-        Some(winner) => Ok(winner),
+    if page.accounts.len() == TICKET_SNAPSHOT_PAGE_LIMIT as usize {
+        TICKET_SNAPSHOT.save(
+            deps.storage,
+            &TicketSnapshot::Reading {
+                pass,
+                start_after: page.accounts.last().cloned(),
+                counted,
+            },
+        )?;
+        return Ok(false);
     }
-}
 
-fn create_player_ranges(deps: &DepsMut, total_tickets: u64) -> PlayerRanges {
-    let mut player_ranges = PlayerRanges::create();
-    let mut current_index = 0;
-    for player_result in get_player_ranges(deps) {
-        let (addr, num_tickets) = player_result.unwrap();
-        let number_of_tickets_to_ration = TOTAL_POOL_SIZE.div(total_tickets).mul(num_tickets);
-        player_ranges.create_player_range(
-            addr,
-            current_index,
-            current_index + number_of_tickets_to_ration,
-        );
-        current_index += number_of_tickets_to_ration
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&ticket_token, &Cw20QueryMsg::TokenInfo {})?;
+    if counted != token_info.total_supply {
+        let snapshot = if pass + 1 < MAX_TICKET_SNAPSHOT_PASSES {
+            TicketSnapshot::Reading {
+                pass: pass + 1,
+                start_after: None,
+                counted: Uint128::zero(),
+            }
+        } else {
+            TicketSnapshot::Failed {}
+        };
+        TICKET_SNAPSHOT.save(deps.storage, &snapshot)?;
+        return Ok(false);
     }
-    player_ranges
+    Ok(true)
 }
 
-fn get_num_tickets(deps: &DepsMut) -> u64 {
-    let players = get_player_ranges(deps);
-    let mut total_num_tickets: u64 = 0;
-    for player_results in players {
-        let (_addr, num_tickets) = player_results.unwrap();
-        total_num_tickets += num_tickets
+// Gives a page of the holders read by the pass their ticket ranges.
+fn assign_ticket_ranges(
+    deps: &mut DepsMut,
+    pass: u32,
+    start_after: Option<Addr>,
+) -> Result<(Vec<Event>, bool), ContractError> {
+    let round = ROUND.load(deps.storage)?;
+    let balances = SNAPSHOT_BALANCES
+        .prefix(round)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(TICKET_SNAPSHOT_PAGE_LIMIT as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut events = vec![];
+    for (holder, (balance_pass, tickets)) in &balances {
+        // Read by an earlier pass, the holder had no tickets left by the last one.
+        if *balance_pass != pass {
+            continue;
+        }
+        let ticket_start = append_ticket_range(deps.storage, round, holder, *tickets)?;
+        record_participation(deps.storage, holder, round, 0, Uint128::zero())?;
+        events.push(ticket_snapshot_event(round, holder, ticket_start, *tickets));
+        PLAYERS.save(deps.storage, (round, holder.clone()), tickets)?;
+    }
+    if balances.len() == TICKET_SNAPSHOT_PAGE_LIMIT as usize {
+        TICKET_SNAPSHOT.save(
+            deps.storage,
+            &TicketSnapshot::Assigning {
+                pass,
+                start_after: balances.last().map(|(holder, _)| holder.clone()),
+            },
+        )?;
+        return Ok((events, false));
     }
-    total_num_tickets
+    TICKET_SNAPSHOT.remove(deps.storage);
+    Ok((events, true))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::TicketCount { addr } => to_binary(&query_ticket_count(deps, _env, addr)?),
-        QueryMsg::LotteryState {} => to_binary(&query_lottery_state(deps, _env)?),
-        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+// Hands out the next `tickets` indices of the round to the owner, returns the first one.
+fn append_ticket_range(
+    storage: &mut dyn Storage,
+    round: u64,
+    owner: &Addr,
+    tickets: u64,
+) -> Result<u64, ContractError> {
+    let start = TOTAL_TICKETS.may_load(storage, round)?.unwrap_or_default();
+    if tickets == 0 {
+        return Ok(start);
     }
+    let end = Uint64::from(start)
+        .checked_add(Uint64::from(tickets))?
+        .u64();
+    ticket_ranges().save(
+        storage,
+        (round, end),
+        &TicketRange {
+            round,
+            owner: owner.clone(),
+            start,
+        },
+    )?;
+    TOTAL_TICKETS.save(storage, round, &end)?;
+    record_ledger_event(storage, None, owner, tickets)?;
+    Ok(start)
 }
 
-pub fn query_lottery_state(deps: Deps, _env: Env) -> StdResult<LotteryStateResponse> {
-    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
-
-    let player_keys = PLAYERS.keys(deps.storage, None, None, cosmwasm_std::Order::Ascending);
-
-    let mut sum: u64 = 0;
+fn record_ledger_event(
+    storage: &mut dyn Storage,
+    sender: Option<&Addr>,
+    owner: &Addr,
+    tickets: u64,
+) -> StdResult<()> {
+    let previous = TICKET_LEDGER_HASH.may_load(storage)?;
+    TICKET_LEDGER_HASH.save(
+        storage,
+        &ledger_hash(previous.as_deref(), sender, owner, tickets),
+    )
+}
 
-    for player_key in player_keys {
-        let key = player_key?;
-        let player = PLAYERS.load(deps.storage, key)?;
-        sum += player
+// Moves `amount` ticket indices from the sender's most recent ranges to the recipient,
+// splitting the last range touched. Range ends never move, so lookups stay valid.
+fn transfer_ticket_ranges(
+    storage: &mut dyn Storage,
+    round: u64,
+    sender: &Addr,
+    recipient: &Addr,
+    amount: u64,
+) -> Result<Vec<(u64, u64)>, ContractError> {
+    let mut moved_ranges = vec![];
+    let mut remaining = amount;
+    while remaining > 0 {
+        let ((_, end), range) = ticket_ranges()
+            .idx
+            .owner
+            .prefix((round, sender.clone()))
+            .range(storage, None, None, Order::Descending)
+            .next()
+            .ok_or(ContractError::TicketTransferInsufficientTickets {})??;
+        let size = end - range.start;
+        let moved = size.min(remaining);
+        if moved < size {
+            ticket_ranges().save(
+                storage,
+                (round, end - moved),
+                &TicketRange {
+                    round,
+                    owner: sender.clone(),
+                    start: range.start,
+                },
+            )?;
+        }
+        ticket_ranges().save(
+            storage,
+            (round, end),
+            &TicketRange {
+                round,
+                owner: recipient.clone(),
+                start: end - moved,
+            },
+        )?;
+        moved_ranges.push((end - moved, end));
+        remaining -= moved;
     }
-
-    Ok(LotteryStateResponse {
-        lotto_state: lottery_state,
-        total_tickets: sum,
-    })
+    record_ledger_event(storage, Some(sender), recipient, amount)?;
+    Ok(moved_ranges)
 }
 
-pub fn query_ticket_count(deps: Deps, _env: Env, addr: Addr) -> StdResult<TicketResponse> {
-    let player_num_tickets = PLAYERS.may_load(deps.storage, addr)?;
-    Ok(TicketResponse {
-        tickets: player_num_tickets,
-    })
+// The owner of a ticket index is found by seeking to the first range ending after it,
+// which costs the same however many ranges the round has.
+fn ticket_owner(storage: &dyn Storage, round: u64, ticket: u64) -> StdResult<Option<Addr>> {
+    ticket_ranges()
+        .prefix(round)
+        .range(
+            storage,
+            Some(Bound::exclusive(ticket)),
+            None,
+            Order::Ascending,
+        )
+        .next()
+        .transpose()
+        .map(|range| range.map(|(_, range)| range.owner))
 }
 
-pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
-    let config = CONFIG.load(deps.storage)?;
-    Ok(ConfigResponse { config })
+fn add_player_tickets(deps: DepsMut, player: &Addr, tickets: u64) -> Result<(), ContractError> {
+    let round = ROUND.load(deps.storage)?;
+    let previous_ticket_count = PLAYERS
+        .may_load(deps.storage, (round, player.clone()))?
+        .unwrap_or_default();
+    let ticket_count = Uint64::from(previous_ticket_count)
+        .checked_add(Uint64::from(tickets))?
+        .u64();
+    PLAYERS.save(deps.storage, (round, player.clone()), &ticket_count)?;
+    record_participation(deps.storage, player, round, 0, Uint128::zero())
 }
 
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coin, coins, Addr};
+// Appends a purchase to the ledger under the next id, and adds it to the lifetime stats.
+fn record_purchase(
+    storage: &mut dyn Storage,
+    env: &Env,
+    round: u64,
+    buyer: &Addr,
+    recipient: &Addr,
+    tickets: u64,
+    paid: Coin,
+) -> Result<Purchase, ContractError> {
+    let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+    stats.tickets_sold = Uint64::from(stats.tickets_sold)
+        .checked_add(Uint64::from(tickets))?
+        .u64();
+    stats.volume = stats.volume.checked_add(paid.amount)?;
+    STATS.save(storage, &stats)?;
+    let tickets_sold = TICKETS_SOLD.may_load(storage, round)?.unwrap_or_default();
+    TICKETS_SOLD.save(
+        storage,
+        round,
+        &Uint64::from(tickets_sold)
+            .checked_add(Uint64::from(tickets))?
+            .u64(),
+    )?;
 
-    use crate::contract::{execute, instantiate, query_ticket_count};
-    use crate::msg::ExecuteMsg;
-    use crate::msg::InstantiateMsg;
-    use crate::test_util::tests::{
-        TestUser, TESTING_DURATION, TESTING_NATIVE_DENOM, TESTING_TICKET_COST, TEST_ADMIN,
+    let id = PURCHASE_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    PURCHASE_COUNT.save(storage, &id)?;
+    let purchase = Purchase {
+        id,
+        round,
+        buyer: buyer.clone(),
+        recipient: recipient.clone(),
+        tickets,
+        paid,
+        height: env.block.height,
+        time: env.block.time,
     };
+    purchases().save(storage, id, &purchase)?;
+    Ok(purchase)
+}
 
-    #[test]
-    fn proper_initialization() {
-        let instantiate_message = InstantiateMsg {
-            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
-            lottery_duration: TESTING_DURATION,
-            admin: TEST_ADMIN.to_string(),
-            house_fee: 500,
-        };
-
-        let mut deps = mock_dependencies();
-        let info = mock_info("creator", &coins(1000, "earth"));
-        let res = instantiate(deps.as_mut(), mock_env(), info, instantiate_message).unwrap();
-        assert_eq!(0, res.messages.len());
+// Indexes the round under the address for PlayerHistory, with what it paid for.
+fn record_participation(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    round: u64,
+    tickets_bought: u64,
+    spent: Uint128,
+) -> Result<(), ContractError> {
+    let first_round = PARTICIPATIONS
+        .prefix(addr.clone())
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+    if first_round {
+        let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+        stats.unique_players += 1;
+        STATS.save(storage, &stats)?;
     }
 
-    #[test]
-    fn buy_tickets() {
-        let instantiate_message = InstantiateMsg {
-            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
-            lottery_duration: TESTING_DURATION,
-            admin: TEST_ADMIN.to_string(),
-            house_fee: 500,
-        };
+    let mut participation = PARTICIPATIONS
+        .may_load(storage, (addr.clone(), round))?
+        .unwrap_or_default();
+    participation.tickets_bought = Uint64::from(participation.tickets_bought)
+        .checked_add(Uint64::from(tickets_bought))?
+        .u64();
+    participation.spent = participation.spent.checked_add(spent)?;
+    PARTICIPATIONS.save(storage, (addr.clone(), round), &participation)?;
+    Ok(())
+}
 
-        let mut deps = mock_dependencies();
+fn execute_transfer_tickets(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: u64,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    if amount == 0 {
+        return Err(ContractError::TicketTransferZeroAmount {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    // cw20 tickets move through the ticket token, the contract can't move them on a holder's behalf.
+    if let TicketMode::Cw20 { .. } = config.ticket_mode {
+        return Err(ContractError::TicketTransferUseTicketToken {});
+    }
 
-        let _ = instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("creator", &coins(1000, "earth")),
-            instantiate_message,
-        )
-        .unwrap();
+    // Ticket ownership is frozen as soon as sales are over, even if the state hasn't moved yet.
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::OPEN { expiration } if !expiration.is_expired(&env.block) => {}
+        _ => return Err(ContractError::TicketTransferNotAvailable {}),
+    }
 
-        let _ = execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("creator", &coins(1000, TESTING_NATIVE_DENOM)),
-            ExecuteMsg::BuyTicket { num_tickets: 1 },
-        )
-        .unwrap();
+    let round = ROUND.load(deps.storage)?;
+    let sender_tickets = PLAYERS
+        .may_load(deps.storage, (round, info.sender.clone()))?
+        .unwrap_or_default();
+    let remaining_tickets = Uint64::from(sender_tickets)
+        .checked_sub(Uint64::from(amount))
+        .map_err(|_| ContractError::TicketTransferInsufficientTickets {})?
+        .u64();
+    if remaining_tickets == 0 {
+        PLAYERS.remove(deps.storage, (round, info.sender.clone()));
+    } else {
+        PLAYERS.save(
+            deps.storage,
+            (round, info.sender.clone()),
+            &remaining_tickets,
+        )?;
+    }
+    let moved_ranges =
+        transfer_ticket_ranges(deps.storage, round, &info.sender, &recipient, amount)?;
+    add_player_tickets(deps, &recipient, amount)?;
 
-        let res = query_ticket_count(
-            deps.as_ref(),
-            mock_env(),
-            Addr::unchecked("creator".to_string()),
-        );
+    Ok(Response::new()
+        .add_attribute("method", "transfer_tickets")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("recipient", recipient.clone())
+        .add_attribute("amount", amount.to_string())
+        .add_event(ticket_transfer_event(
+            round,
+            &info.sender,
+            &recipient,
+            amount,
+            &moved_ranges,
+        )))
+}
 
-        assert!(res.is_ok());
-        let ticket_response = res.unwrap();
-        assert_eq!(ticket_response.tickets, Some(1))
+fn execute_lottery(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    seed: u64,
+    salt: HexBinary,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CHOOSING {} => {
+            match config.randomness {
+                RandomnessSource::AdminSeed {} => is_admin(info.sender.clone(), config)?,
+                RandomnessSource::CommitReveal {} => {
+                    // The seed was committed before any tickets were sold, so it can't be picked
+                    // after seeing who entered. Anyone given the seed and salt can reveal them.
+                    verify_seed_reveal(&SEED_COMMITMENT.load(deps.storage)?, seed, &salt)?;
+                }
+                RandomnessSource::Drand(_) => {
+                    return Err(ContractError::DrawRequiresDrandBeacon {})
+                }
+                RandomnessSource::Ibc(_) => {
+                    return Err(ContractError::DrawRequiresIbcRandomness {})
+                }
+                RandomnessSource::OperatorSignature(_) => {
+                    return Err(ContractError::DrawRequiresOperatorSignature {})
+                }
+                RandomnessSource::Oracle(_) => return Err(ContractError::DrawRequiresOracle {}),
+            }
+            let draw = draw_winner(deps.branch(), &env, seed_entropy(seed, &salt))?;
+            Ok(pay_keeper_bounty(deps, &info, Response::new())?
+                .add_submessages(draw.hook_msgs)
+                .add_event(draw.event)
+                .add_attribute("method", "execute_lottery")
+                .add_attribute("winner", draw.winner))
+        }
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::DrawDeadlinePassed {}),
     }
+}
 
-    #[test]
-    fn buy_multiple_tickets() {
-        let mut deps = mock_dependencies();
+fn execute_submit_drand_beacon(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    signature: HexBinary,
+    previous_signature: HexBinary,
+) -> Result<Response, ContractError> {
+    let drand = match CONFIG.load(deps.storage)?.randomness {
+        RandomnessSource::Drand(drand) => drand,
+        _ => return Err(ContractError::DrandNotConfigured {}),
+    };
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CHOOSING {} => {
+            let drand_round = DRAND_ROUND.load(deps.storage)?;
+            let randomness =
+                verify_drand_beacon(&drand, drand_round, &previous_signature, &signature)?;
+            let draw = draw_winner(deps.branch(), &env, randomness)?;
+            Ok(pay_keeper_bounty(deps, &info, Response::new())?
+                .add_submessages(draw.hook_msgs)
+                .add_event(draw.event)
+                .add_attribute("method", "submit_drand_beacon")
+                .add_attribute("drand_round", drand_round.to_string())
+                .add_attribute("winner", draw.winner))
+        }
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::DrawDeadlinePassed {}),
+    }
+}
 
+fn execute_submit_operator_signature(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    signature: HexBinary,
+) -> Result<Response, ContractError> {
+    let operator = match CONFIG.load(deps.storage)?.randomness {
+        RandomnessSource::OperatorSignature(operator) => operator,
+        _ => return Err(ContractError::OperatorSignatureNotConfigured {}),
+    };
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CHOOSING {} => {
+            // The signed message is fixed by the ledger at close time, the nonce when the
+            // round started.
+            let round = ROUND.load(deps.storage)?;
+            let message = operator_message(
+                &env.contract.address,
+                round,
+                &round_ledger_hash(deps.storage)?,
+            );
+            let randomness = verify_operator_signature(
+                deps.api,
+                &operator,
+                &SEED_COMMITMENT.load(deps.storage)?,
+                &message,
+                &signature,
+            )?;
+            let draw = draw_winner(deps.branch(), &env, randomness)?;
+            Ok(pay_keeper_bounty(deps, &info, Response::new())?
+                .add_submessages(draw.hook_msgs)
+                .add_event(draw.event)
+                .add_attribute("method", "submit_operator_signature")
+                .add_attribute("winner", draw.winner))
+        }
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::DrawDeadlinePassed {}),
+    }
+}
+
+fn execute_oracle_callback(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    callback: OracleCallback,
+) -> Result<Response, ContractError> {
+    let oracle = match CONFIG.load(deps.storage)?.randomness {
+        RandomnessSource::Oracle(oracle) => oracle,
+        _ => return Err(ContractError::OracleNotConfigured {}),
+    };
+    if info.sender != oracle.contract {
+        return Err(ContractError::Unauthorized {});
+    }
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CHOOSING {} => {
+            if callback.job_id != oracle_job_id(ROUND.load(deps.storage)?) {
+                return Err(ContractError::OracleJobMismatch {
+                    job_id: callback.job_id,
+                });
+            }
+            let randomness: [u8; 32] = callback
+                .randomness
+                .to_array()
+                .map_err(|_| ContractError::InvalidRandomness {})?;
+            let draw = draw_winner(deps, &env, randomness)?;
+            Ok(Response::new()
+                .add_submessages(draw.hook_msgs)
+                .add_event(draw.event)
+                .add_attribute("method", "oracle_callback")
+                .add_attribute("job_id", callback.job_id)
+                .add_attribute("winner", draw.winner))
+        }
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::DrawDeadlinePassed {}),
+    }
+}
+
+// A round nobody entered still has a ledger hash, the initial 32 zero bytes.
+fn round_ledger_hash(storage: &dyn Storage) -> StdResult<HexBinary> {
+    Ok(TICKET_LEDGER_HASH
+        .may_load(storage)?
+        .unwrap_or_else(|| HexBinary::from([0u8; 32])))
+}
+
+// What a draw adds to the response of the message that provided its randomness.
+pub(crate) struct Draw {
+    pub winner: Addr,
+    pub event: Event,
+    pub hook_msgs: Vec<SubMsg>,
+}
+
+// Every randomness source hands its 32 bytes of entropy to the same draw.
+pub(crate) fn draw_winner(
+    deps: DepsMut,
+    env: &Env,
+    source_entropy: [u8; 32],
+) -> Result<Draw, ContractError> {
+    // Once the deadline has passed the round is only waiting for ExpireDraw, drawing it late
+    // would let whoever provides the randomness decide whether the round happens at all.
+    if DRAW_DEADLINE.load(deps.storage)?.is_expired(&env.block) {
+        return Err(ContractError::DrawDeadlinePassed {});
+    }
+    let round = ROUND.load(deps.storage)?;
+    let participant_entropy = PARTICIPANT_ENTROPY
+        .may_load(deps.storage)?
+        .map(|participant_entropy| participant_entropy.accumulator);
+    let entropy = mix_entropy(&source_entropy, participant_entropy.as_deref());
+    let total_tickets = TOTAL_TICKETS
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    let winning_ticket = winning_ticket(&entropy, round, total_tickets)
+        .ok_or(ContractError::WinnerNotPossibleToFind {})?;
+    let winner = choose_winner(&deps, round, winning_ticket)?;
+
+    let draw = DrawRecord {
+        version: DRAW_VERSION,
+        source_entropy: HexBinary::from(source_entropy),
+        participant_entropy,
+        entropy: HexBinary::from(entropy),
+        total_tickets,
+        winning_ticket,
+        winner: winner.clone(),
+    };
+    DRAWS.save(deps.storage, round, &draw)?;
+    LOTTERY_STATE.save(
+        deps.storage,
+        &LotteryState::CLOSED {
+            winner: winner.clone(),
+            claimed: false,
+        },
+    )?;
+    Ok(Draw {
+        event: draw_event(round, &draw),
+        hook_msgs: hook_msgs(
+            deps.storage,
+            LotteryHookMsg::WinnerChosen {
+                round,
+                winner: winner.clone(),
+                winning_ticket,
+            },
+        )?,
+        winner,
+    })
+}
+
+fn execute_start_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    seed_commitment: Option<HexBinary>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    is_admin(info.sender.clone(), config.clone())?;
+
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::REFUNDING { .. } if !refunds_settled(deps.as_ref(), &env)? => {
+            Err(ContractError::RoundNotFinished {})
+        }
+        LotteryState::CLOSED { claimed: true, .. } | LotteryState::REFUNDING { .. } => {
+            let seed_commitment = round_seed_commitment(&config, seed_commitment)?;
+            let round = ROUND.load(deps.storage)?;
+            // Refunds nobody claimed yet are held back from the next rounds' pools.
+            if let Some(refund_pool) = REFUND_POOLS.may_load(deps.storage, round)? {
+                let reserved = RESERVED_REFUNDS.may_load(deps.storage)?.unwrap_or_default();
+                RESERVED_REFUNDS.save(
+                    deps.storage,
+                    &reserved.checked_add(refund_pool.outstanding)?,
+                )?;
+            }
+            let round = round + 1;
+            let (round_started_event, ticket_token_msg) =
+                open_round(deps.storage, &env, &config, round, seed_commitment)?;
+            post_bond(deps.storage, &info, &config, round)?;
+            Ok(Response::new()
+                .add_attribute("method", "start_round")
+                .add_attribute("round", round.to_string())
+                .add_event(round_started_event)
+                .add_submessages(ticket_token_msg))
+        }
+        _ => Err(ContractError::RoundNotFinished {}),
+    }
+}
+
+fn execute_set_randomness_source(
+    deps: DepsMut,
+    info: MessageInfo,
+    randomness: RandomnessSource,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    is_admin(info.sender, config.clone())?;
+
+    // Switching while a round is open or being drawn would change the rules for tickets
+    // already sold, the new source is used from the next StartRound on.
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CLOSED { .. } | LotteryState::REFUNDING { .. } => {
+            config.randomness = validate_randomness_source(deps.api, randomness)?;
+            CONFIG.save(deps.storage, &config)?;
+            Ok(Response::new()
+                .add_attribute("method", "set_randomness_source")
+                .add_event(config_change_event(ROUND.load(deps.storage)?, &config)?))
+        }
+        _ => Err(ContractError::RoundNotFinished {}),
+    }
+}
+
+fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    gas_limit: Option<u64>,
+) -> Result<Response, ContractError> {
+    is_admin(info.sender, CONFIG.load(deps.storage)?)?;
+    let addr = deps.api.addr_validate(&addr)?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    if hooks.iter().any(|hook| hook.addr == addr) {
+        return Err(ContractError::HookAlreadyRegistered {});
+    }
+    if hooks.len() >= MAX_HOOKS {
+        return Err(ContractError::TooManyHooks { max: MAX_HOOKS });
+    }
+    let gas_limit = gas_limit.unwrap_or(DEFAULT_HOOK_GAS_LIMIT);
+    hooks.push(Hook {
+        addr: addr.clone(),
+        gas_limit,
+    });
+    HOOKS.save(deps.storage, &hooks)?;
+    Ok(Response::new()
+        .add_attribute("method", "add_hook")
+        .add_attribute("hook", addr)
+        .add_attribute("gas_limit", gas_limit.to_string()))
+}
+
+fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    is_admin(info.sender, CONFIG.load(deps.storage)?)?;
+    let addr = deps.api.addr_validate(&addr)?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    if !hooks.iter().any(|hook| hook.addr == addr) {
+        return Err(ContractError::HookNotRegistered {});
+    }
+    hooks.retain(|hook| hook.addr != addr);
+    HOOKS.save(deps.storage, &hooks)?;
+    Ok(Response::new()
+        .add_attribute("method", "remove_hook")
+        .add_attribute("hook", addr))
+}
+
+// One notification per hook contract. They're sent as gas limited submessages replying on
+// error, so a failing hook can't block purchases, draws or claims.
+fn hook_msgs(storage: &dyn Storage, msg: LotteryHookMsg) -> StdResult<Vec<SubMsg>> {
+    HOOKS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|hook| {
+            Ok(
+                SubMsg::reply_on_error(msg.clone().into_cosmos_msg(hook.addr)?, HOOK_REPLY_ID)
+                    .with_gas_limit(hook.gas_limit),
+            )
+        })
+        .collect()
+}
+
+fn execute_claim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CLOSED { winner, claimed } => {
+            handle_lottery_claim(deps, &env, info, winner, claimed)
+        }
+        LotteryState::CHOOSING {} => Err(ContractError::LotteryNotClaimable {}),
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotClaimable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::LotteryNotClaimable {}),
+    }
+}
+
+fn execute_expire_draw(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    match lottery_state {
+        LotteryState::CHOOSING {} => {
+            let round = ROUND.load(deps.storage)?;
+            let total_tickets = TOTAL_TICKETS
+                .may_load(deps.storage, round)?
+                .unwrap_or_default();
+            // A round without tickets has nothing to draw, it can be expired right away.
+            if total_tickets > 0 && !DRAW_DEADLINE.load(deps.storage)?.is_expired(&env.block) {
+                return Err(ContractError::DrawDeadlineNotReached {});
+            }
+            LOTTERY_STATE.save(
+                deps.storage,
+                &LotteryState::REFUNDING {
+                    refunded_tickets: 0,
+                },
+            )?;
+            let config = CONFIG.load(deps.storage)?;
+            let mut outstanding = config
+                .ticket_unit_cost
+                .amount
+                .checked_mul(Uint128::from(total_tickets))?;
+            // Missing the deadline, e.g. by never revealing the committed seed, costs the
+            // admin their bond. Without tickets there's nobody to share it with, so it's sent
+            // back instead.
+            let mut bond_msg = None;
+            let mut bond = BONDS.may_load(deps.storage, round)?;
+            if let Some(bond) = &mut bond {
+                if total_tickets == 0 {
+                    bond.status = BondStatus::Returned {};
+                    bond_msg = Some(BankMsg::Send {
+                        to_address: config.admin.to_string(),
+                        amount: vec![bond.amount.clone()],
+                    });
+                } else {
+                    bond.status = BondStatus::Slashed {};
+                    outstanding = outstanding.checked_add(bond.amount.amount)?;
+                }
+                BONDS.save(deps.storage, round, bond)?;
+            }
+            if let Some(bounties) = KEEPER_BOUNTIES.may_load(deps.storage, round)? {
+                outstanding = outstanding.checked_sub(bounties)?;
+            }
+            let refund_pool = RefundPool {
+                outstanding,
+                total_tickets,
+                refunded_tickets: 0,
+                from_purchases: false,
+                claim_deadline: config.refund_window.after(&env.block),
+            };
+            REFUND_POOLS.save(deps.storage, round, &refund_pool)?;
+            Ok(Response::new()
+                .add_attribute("method", "expire_draw")
+                .add_attribute("round", round.to_string())
+                .add_event(draw_expired_event(
+                    round,
+                    total_tickets,
+                    &refund_pool,
+                    bond.as_ref(),
+                    &config.ticket_unit_cost.denom,
+                ))
+                .add_messages(bond_msg))
+        }
+        LotteryState::OPEN { expiration } if expiration.is_expired(&env.block) => {
+            expire_unclosed_sales(deps, env)
+        }
+        _ => Err(ContractError::LotteryNotExecutable {}),
+    }
+}
+
+// Sales that couldn't be closed by the draw deadline, i.e. a cw20 snapshot that never added
+// up, are refunded from the purchase ledger: every ticket goes back to whoever paid for it.
+// The admin had no part in it, so the bond is returned rather than slashed, and no keeper
+// bounty was paid yet.
+fn expire_unclosed_sales(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let snapshot_failed = matches!(
+        TICKET_SNAPSHOT.may_load(deps.storage)?,
+        Some(TicketSnapshot::Failed {})
+    );
+    let deadline_passed = match DRAW_DEADLINE.may_load(deps.storage)? {
+        Some(draw_deadline) => draw_deadline.is_expired(&env.block),
+        None => false,
+    };
+    if !snapshot_failed && !deadline_passed {
+        return Err(ContractError::DrawDeadlineNotReached {});
+    }
+    let round = ROUND.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    TICKET_SNAPSHOT.remove(deps.storage);
+    LOTTERY_STATE.save(
+        deps.storage,
+        &LotteryState::REFUNDING {
+            refunded_tickets: 0,
+        },
+    )?;
+    let tickets_sold = TICKETS_SOLD
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    let refund_pool = RefundPool {
+        outstanding: config
+            .ticket_unit_cost
+            .amount
+            .checked_mul(Uint128::from(tickets_sold))?,
+        total_tickets: tickets_sold,
+        refunded_tickets: 0,
+        from_purchases: true,
+        claim_deadline: config.refund_window.after(&env.block),
+    };
+    REFUND_POOLS.save(deps.storage, round, &refund_pool)?;
+
+    let mut bond_msg = None;
+    let mut bond = BONDS.may_load(deps.storage, round)?;
+    if let Some(bond) = &mut bond {
+        bond.status = BondStatus::Returned {};
+        BONDS.save(deps.storage, round, bond)?;
+        bond_msg = Some(BankMsg::Send {
+            to_address: config.admin.to_string(),
+            amount: vec![bond.amount.clone()],
+        });
+    }
+    Ok(Response::new()
+        .add_attribute("method", "expire_draw")
+        .add_attribute("round", round.to_string())
+        .add_event(draw_expired_event(
+            round,
+            tickets_sold,
+            &refund_pool,
+            bond.as_ref(),
+            &config.ticket_unit_cost.denom,
+        ))
+        .add_messages(bond_msg))
+}
+
+// Every ticket is paid back at the ticket cost, to whoever held it when sales closed,
+// along with its share of the admin's slashed bond. When sales never closed, it's paid back
+// to whoever bought it. Refunds of past rounds stay claimable after the next round started.
+fn execute_claim_refund(
+    deps: DepsMut,
+    info: MessageInfo,
+    round: Option<u64>,
+) -> Result<Response, ContractError> {
+    let current_round = ROUND.load(deps.storage)?;
+    let round = round.unwrap_or(current_round);
+    let mut refund_pool = REFUND_POOLS
+        .may_load(deps.storage, round)?
+        .ok_or(ContractError::RefundNotAvailable {})?;
+    let refunded_tickets = refund_pool.refunded_tickets;
+    if REFUNDS.has(deps.storage, (round, info.sender.clone())) {
+        return Err(ContractError::RefundAlreadyClaimed {});
+    }
+    let tickets = match refund_pool.from_purchases {
+        true => PARTICIPATIONS
+            .may_load(deps.storage, (info.sender.clone(), round))?
+            .map(|participation| participation.tickets_bought)
+            .filter(|tickets_bought| *tickets_bought > 0),
+        false => PLAYERS.may_load(deps.storage, (round, info.sender.clone()))?,
+    }
+    .ok_or(ContractError::NothingToRefund {})?;
+
+    let ticket_cost = CONFIG.load(deps.storage)?.ticket_unit_cost;
+    let mut refund = ticket_cost.amount.checked_mul(Uint128::from(tickets))?;
+    if let Some(Bond {
+        amount,
+        status: BondStatus::Slashed {},
+    }) = BONDS.may_load(deps.storage, round)?
+    {
+        refund = refund.checked_add(ticket_share(
+            amount.amount,
+            refunded_tickets,
+            tickets,
+            refund_pool.total_tickets,
+        ))?;
+    }
+    // Keepers were paid out of the ticket payments, every ticket bears its part.
+    if let Some(bounties) = KEEPER_BOUNTIES.may_load(deps.storage, round)? {
+        refund = refund.checked_sub(ticket_share(
+            bounties,
+            refunded_tickets,
+            tickets,
+            refund_pool.total_tickets,
+        ))?;
+    }
+    REFUNDS.save(deps.storage, (round, info.sender.clone()), &refund)?;
+    refund_pool.refunded_tickets += tickets;
+    refund_pool.outstanding = refund_pool.outstanding.checked_sub(refund)?;
+    REFUND_POOLS.save(deps.storage, round, &refund_pool)?;
+    if round == current_round {
+        LOTTERY_STATE.save(
+            deps.storage,
+            &LotteryState::REFUNDING {
+                refunded_tickets: refund_pool.refunded_tickets,
+            },
+        )?;
+    } else {
+        let reserved = RESERVED_REFUNDS.may_load(deps.storage)?.unwrap_or_default();
+        RESERVED_REFUNDS.save(deps.storage, &reserved.checked_sub(refund)?)?;
+    }
+
+    let refund = Coin {
+        denom: ticket_cost.denom,
+        amount: refund,
+    };
+    Ok(Response::new()
+        .add_attribute("method", "claim_refund")
+        .add_attribute("player", info.sender.clone())
+        .add_attribute("tickets", tickets.to_string())
+        .add_event(refund_event(round, &info.sender, tickets, &refund))
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![refund],
+        }))
+}
+
+fn handle_lottery_claim(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    winner: Addr,
+    claimed: bool,
+) -> Result<Response, ContractError> {
+    if !claimed {
+        if info.sender == winner {
+            // send contract funds, and update lottery state to "closed and claimed"
+            LOTTERY_STATE.save(
+                deps.storage,
+                &LotteryState::CLOSED {
+                    winner,
+                    claimed: true,
+                },
+            )?;
+
+            // The payout is the one shown by the PayoutPreview query.
+            let payout = query_payout_preview(deps.as_ref(), env.clone())?;
+
+            record_claim(deps.storage, &info.sender, &payout)?;
+            let claim_event = claim_event(&info.sender, &payout);
+            let hook_msgs = hook_msgs(
+                deps.storage,
+                LotteryHookMsg::PrizeClaimed {
+                    round: payout.round,
+                    winner: info.sender.clone(),
+                    prize: payout.winner_amount.clone(),
+                },
+            )?;
+
+            let disperse_reward_msg = SubMsg::new(BankMsg::Send {
+                to_address: String::from(info.sender),
+                amount: vec![payout.winner_amount],
+            });
+
+            let mut response: Response = Default::default();
+
+            response.messages = vec![disperse_reward_msg];
+            for fee in payout.fees {
+                response.messages.push(SubMsg::new(BankMsg::Send {
+                    to_address: String::from(fee.recipient),
+                    amount: vec![fee.amount],
+                }));
+            }
+
+            // The admin's bond isn't part of the prize, it goes back to them with the fee.
+            if let Some(bond) = payout.bond {
+                BONDS.update(deps.storage, payout.round, |posted| -> StdResult<_> {
+                    let mut posted = posted.ok_or_else(|| StdError::not_found("bond"))?;
+                    posted.status = BondStatus::Returned {};
+                    Ok(posted)
+                })?;
+                response.messages.push(SubMsg::new(BankMsg::Send {
+                    to_address: String::from(CONFIG.load(deps.storage)?.admin),
+                    amount: vec![bond],
+                }));
+            }
+
+            Ok(response.add_submessages(hook_msgs).add_event(claim_event))
+        } else {
+            Err(ContractError::LotteryNotClaimedByCorrectUser {})
+        }
+    } else {
+        Err(ContractError::LotteryAlreadyClaimed {})
+    }
+}
+
+// Adds a claimed payout to the lifetime stats and the winner's leaderboard entry.
+fn record_claim(
+    storage: &mut dyn Storage,
+    winner: &Addr,
+    payout: &PayoutPreviewResponse,
+) -> Result<(), ContractError> {
+    let won = payout.winner_amount.amount;
+    let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+    for fee in &payout.fees {
+        stats.fees_paid = stats.fees_paid.checked_add(fee.amount.amount)?;
+    }
+    stats.largest_jackpot = stats.largest_jackpot.max(won);
+    STATS.save(storage, &stats)?;
+
+    winnings().update(storage, winner.clone(), |winnings| -> StdResult<_> {
+        let mut winnings = winnings.unwrap_or(Winnings {
+            addr: winner.clone(),
+            total_won: Uint128::zero(),
+            wins: 0,
+        });
+        winnings.total_won = winnings.total_won.checked_add(won)?;
+        winnings.wins += 1;
+        Ok(winnings)
+    })?;
+    Ok(())
+}
+
+// The current round's refunds were all claimed or its refund window has passed.
+fn refunds_settled(deps: Deps, env: &Env) -> StdResult<bool> {
+    let round = ROUND.load(deps.storage)?;
+    Ok(match REFUND_POOLS.may_load(deps.storage, round)? {
+        Some(refund_pool) => {
+            refund_pool.claim_deadline.is_expired(&env.block)
+                || refund_pool.refunded_tickets >= refund_pool.total_tickets
+        }
+        None => true,
+    })
+}
+
+// The contract's balance in the ticket denom, without the admin's bond while it's held and
+// the refunds still owed for past rounds.
+fn round_prize_pool(deps: Deps, env: &Env, config: &Config, round: u64) -> StdResult<Uint128> {
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &config.ticket_unit_cost.denom)?;
+    let bond = match BONDS.may_load(deps.storage, round)? {
+        Some(Bond {
+            amount,
+            status: BondStatus::Posted {},
+        }) => amount.amount,
+        _ => Uint128::zero(),
+    };
+    let reserved = RESERVED_REFUNDS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(balance.amount.saturating_sub(bond).saturating_sub(reserved))
+}
+
+// Adds the configured bounty for a keeper closing sales or drawing the round. It comes out of
+// the round's ticket payments, never out of the contract's balance: coins sent to the contract
+// directly can't grow it past what the round's tickets paid in.
+fn pay_keeper_bounty(
+    deps: DepsMut,
+    info: &MessageInfo,
+    response: Response,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let total_tickets = TOTAL_TICKETS
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    let paid = KEEPER_BOUNTIES
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    let bounty = keeper_bounty_due(&config, total_tickets, paid)?;
+    if bounty.is_zero() {
+        return Ok(response);
+    }
+    KEEPER_BOUNTIES.save(deps.storage, round, &paid.checked_add(bounty)?)?;
+    let bounty = Coin {
+        denom: config.ticket_unit_cost.denom,
+        amount: bounty,
+    };
+    Ok(response
+        .add_event(keeper_bounty_event(round, &info.sender, &bounty))
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![bounty],
+        }))
+}
+
+// The bounty of the next keeper call, out of the payments for `total_tickets` less the
+// bounties already paid.
+fn keeper_bounty_due(config: &Config, total_tickets: u64, paid: Uint128) -> StdResult<Uint128> {
+    let prize_pool = config
+        .ticket_unit_cost
+        .amount
+        .checked_mul(Uint128::from(total_tickets))?
+        .saturating_sub(paid);
+    Ok(match config.keeper_bounty {
+        Some(KeeperBounty::PoolShare(share)) => prize_pool * share,
+        Some(KeeperBounty::Fixed(amount)) => amount.min(prize_pool),
+        None => Uint128::zero(),
+    })
+}
+
+// The bounties of the keeper calls the round still has ahead of it: closing sales while it's
+// open, then drawing unless an oracle or IBC callback does it.
+fn pending_keeper_bounties(deps: Deps, config: &Config, round: u64) -> StdResult<Uint128> {
+    let (keeper_calls, total_tickets) = match LOTTERY_STATE.load(deps.storage)? {
+        // cw20 tickets are only counted once sales close, the ones sold so far stand in.
+        LotteryState::OPEN { .. } => (2, TICKETS_SOLD.may_load(deps.storage, round)?),
+        LotteryState::CHOOSING {} => (1, TOTAL_TICKETS.may_load(deps.storage, round)?),
+        _ => return Ok(Uint128::zero()),
+    };
+    let keeper_calls = match config.randomness {
+        RandomnessSource::Oracle(_) | RandomnessSource::Ibc(_) => keeper_calls - 1,
+        _ => keeper_calls,
+    };
+    let mut paid = KEEPER_BOUNTIES
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+    let mut pending = Uint128::zero();
+    for _ in 0..keeper_calls {
+        let bounty = keeper_bounty_due(config, total_tickets.unwrap_or_default(), paid)?;
+        paid += bounty;
+        pending += bounty;
+    }
+    Ok(pending)
+}
+
+fn choose_winner(deps: &DepsMut, round: u64, winner_ticket: u64) -> Result<Addr, ContractError> {
+    ticket_owner(deps.storage, round, winner_ticket)?
+        .ok_or(ContractError::WinnerNotPossibleToFind {})
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::TicketCount { addr } => to_binary(&query_ticket_count(deps, _env, addr)?),
+        QueryMsg::LotteryState {} => to_binary(&query_lottery_state(deps, _env)?),
+        QueryMsg::Status {} => to_binary(&query_status(deps, _env)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Players {
+            round,
+            start_after,
+            limit,
+        } => to_binary(&query_players(deps, round, start_after, limit)?),
+        QueryMsg::RandomnessRequest {} => to_binary(&query_randomness_request(deps)?),
+        QueryMsg::ParticipantEntropy {} => to_binary(&query_participant_entropy(deps)?),
+        QueryMsg::PlayerHistory {
+            addr,
+            start_after,
+            limit,
+        } => to_binary(&query_player_history(deps, _env, addr, start_after, limit)?),
+        QueryMsg::Purchases {
+            round,
+            start_after,
+            limit,
+        } => to_binary(&query_purchases(deps, round, start_after, limit)?),
+        QueryMsg::Purchase { id } => to_binary(&query_purchase(deps, id)?),
+        QueryMsg::Hooks {} => to_binary(&query_hooks(deps)?),
+        QueryMsg::Stats {} => to_binary(&query_stats(deps)?),
+        QueryMsg::TopWinners { limit } => to_binary(&query_top_winners(deps, limit)?),
+        QueryMsg::Odds { addr } => to_binary(&query_odds(deps, _env, addr)?),
+        QueryMsg::SimulatePurchase { addr, num_tickets } => {
+            to_binary(&query_simulate_purchase(deps, _env, addr, num_tickets)?)
+        }
+        QueryMsg::OperatorMessage {} => to_binary(&query_operator_message(deps, _env)?),
+        QueryMsg::PayoutPreview {} => to_binary(&query_payout_preview(deps, _env)?),
+        QueryMsg::Bond { round } => to_binary(&query_bond(deps, round)?),
+        QueryMsg::VerifyDraw { round } => to_binary(&query_verify_draw(deps, round)?),
+    }
+}
+
+pub fn query_lottery_state(deps: Deps, _env: Env) -> StdResult<LotteryStateResponse> {
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let drand_round = DRAND_ROUND.may_load(deps.storage)?;
+    let draw_deadline = DRAW_DEADLINE.may_load(deps.storage)?;
+
+    // Until the snapshot is taken cw20 tickets are only tracked by the ticket token.
+    if let Some(ticket_token) = unsnapshotted_ticket_token(deps, &lottery_state)? {
+        let token_info: TokenInfoResponse = deps
+            .querier
+            .query_wasm_smart(ticket_token, &Cw20QueryMsg::TokenInfo {})?;
+        return Ok(LotteryStateResponse {
+            lotto_state: lottery_state,
+            total_tickets: Uint64::try_from(token_info.total_supply)?.u64(),
+            round,
+            drand_round,
+            draw_deadline,
+        });
+    }
+
+    let total_tickets = TOTAL_TICKETS
+        .may_load(deps.storage, round)?
+        .unwrap_or_default();
+
+    Ok(LotteryStateResponse {
+        lotto_state: lottery_state,
+        total_tickets,
+        round,
+        drand_round,
+        draw_deadline,
+    })
+}
+
+pub fn query_status(deps: Deps, env: Env) -> StdResult<StatusResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let mut valid_actions = vec![];
+    let mut draw_executable = false;
+
+    let (phase, ends_at) = match LOTTERY_STATE.load(deps.storage)? {
+        LotteryState::OPEN { expiration } if !expiration.is_expired(&env.block) => {
+            valid_actions.push("buy_ticket");
+            if let TicketMode::Native {} = config.ticket_mode {
+                valid_actions.push("transfer_tickets");
+            }
+            (Phase::Open {}, Some(expiration))
+        }
+        LotteryState::OPEN { .. } => {
+            let snapshot_failed = matches!(
+                TICKET_SNAPSHOT.may_load(deps.storage)?,
+                Some(TicketSnapshot::Failed {})
+            );
+            match DRAW_DEADLINE.may_load(deps.storage)? {
+                Some(draw_deadline) if snapshot_failed || draw_deadline.is_expired(&env.block) => {
+                    valid_actions.push("expire_draw");
+                    (Phase::DrawExpired {}, None)
+                }
+                draw_deadline => {
+                    valid_actions.push("close_sales");
+                    (Phase::SalesEnded {}, draw_deadline)
+                }
+            }
+        }
+        LotteryState::CHOOSING {} => {
+            let draw_deadline = DRAW_DEADLINE.load(deps.storage)?;
+            let total_tickets = TOTAL_TICKETS
+                .may_load(deps.storage, round)?
+                .unwrap_or_default();
+            if total_tickets == 0 || draw_deadline.is_expired(&env.block) {
+                valid_actions.push("expire_draw");
+                (Phase::DrawExpired {}, None)
+            } else {
+                // Oracle and IBC draws are triggered by the provider, not by a message.
+                match &config.randomness {
+                    RandomnessSource::AdminSeed {} | RandomnessSource::CommitReveal {} => {
+                        valid_actions.push("execute_lottery");
+                        draw_executable = true;
+                    }
+                    RandomnessSource::Drand(drand) => {
+                        let drand_round = DRAND_ROUND.load(deps.storage)?;
+                        if drand_round_time(drand, drand_round) <= env.block.time {
+                            valid_actions.push("submit_drand_beacon");
+                            draw_executable = true;
+                        }
+                    }
+                    RandomnessSource::OperatorSignature(_) => {
+                        valid_actions.push("submit_operator_signature");
+                        draw_executable = true;
+                    }
+                    RandomnessSource::Ibc(_) => {
+                        if let None | Some(RandomnessRequestStatus::Failed {}) =
+                            RANDOMNESS_REQUEST.may_load(deps.storage)?
+                        {
+                            valid_actions.push("request_randomness");
+                        }
+                    }
+                    RandomnessSource::Oracle(_) => {}
+                }
+                (Phase::Choosing {}, Some(draw_deadline))
+            }
+        }
+        LotteryState::CLOSED { claimed: false, .. } => {
+            valid_actions.extend(["claim_tokens", "set_randomness_source"]);
+            (Phase::Drawn {}, None)
+        }
+        LotteryState::CLOSED { claimed: true, .. } => {
+            valid_actions.extend(["start_round", "set_randomness_source"]);
+            (Phase::Claimed {}, None)
+        }
+        LotteryState::REFUNDING { refunded_tickets } => {
+            let refund_pool = REFUND_POOLS.may_load(deps.storage, round)?;
+            if let Some(refund_pool) = &refund_pool {
+                if refunded_tickets < refund_pool.total_tickets {
+                    valid_actions.push("claim_refund");
+                }
+            }
+            if refunds_settled(deps, &env)? {
+                valid_actions.push("start_round");
+            }
+            valid_actions.push("set_randomness_source");
+            let claim_deadline = refund_pool.map(|refund_pool| refund_pool.claim_deadline);
+            (Phase::Refunding {}, claim_deadline)
+        }
+    };
+
+    let (seconds_remaining, blocks_remaining) = match ends_at {
+        Some(Expiration::AtTime(time)) => (
+            Some(time.seconds().saturating_sub(env.block.time.seconds())),
+            None,
+        ),
+        Some(Expiration::AtHeight(height)) => (None, Some(height.saturating_sub(env.block.height))),
+        _ => (None, None),
+    };
+    Ok(StatusResponse {
+        round,
+        phase,
+        ends_at,
+        seconds_remaining,
+        blocks_remaining,
+        draw_executable,
+        valid_actions: valid_actions.into_iter().map(String::from).collect(),
+    })
+}
+
+pub fn query_ticket_count(deps: Deps, _env: Env, addr: Addr) -> StdResult<TicketResponse> {
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    if let Some(ticket_token) = unsnapshotted_ticket_token(deps, &lottery_state)? {
+        let balance: BalanceResponse = deps.querier.query_wasm_smart(
+            ticket_token,
+            &Cw20QueryMsg::Balance {
+                address: addr.to_string(),
+            },
+        )?;
+        let tickets = Uint64::try_from(balance.balance)?.u64();
+        return Ok(TicketResponse {
+            tickets: Some(tickets).filter(|tickets| *tickets > 0),
+        });
+    }
+
+    let round = ROUND.load(deps.storage)?;
+    let player_num_tickets = PLAYERS.may_load(deps.storage, (round, addr))?;
+    Ok(TicketResponse {
+        tickets: player_num_tickets,
+    })
+}
+
+pub fn query_players(
+    deps: Deps,
+    round: Option<u64>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PlayersResponse> {
+    let current_round = ROUND.load(deps.storage)?;
+    let round = round.unwrap_or(current_round);
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT);
+    let start_after = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+
+    // Until the snapshot is taken cw20 tickets are only tracked by the ticket token,
+    // which lists its holders in the same order.
+    let lottery_state = LOTTERY_STATE.load(deps.storage)?;
+    if let Some(ticket_token) = unsnapshotted_ticket_token(deps, &lottery_state)? {
+        if round == current_round {
+            let page: AllAccountsResponse = deps.querier.query_wasm_smart(
+                &ticket_token,
+                &Cw20QueryMsg::AllAccounts {
+                    start_after: start_after.map(String::from),
+                    limit: Some(limit),
+                },
+            )?;
+            let mut players = vec![];
+            for addr in page.accounts {
+                let balance: BalanceResponse = deps.querier.query_wasm_smart(
+                    &ticket_token,
+                    &Cw20QueryMsg::Balance {
+                        address: addr.clone(),
+                    },
+                )?;
+                // Holders that sent all their tickets away stay listed by the token.
+                let tickets = Uint64::try_from(balance.balance)?.u64();
+                if tickets > 0 {
+                    players.push(PlayerTickets {
+                        addr: deps.api.addr_validate(&addr)?,
+                        tickets,
+                    });
+                }
+            }
+            return Ok(PlayersResponse { round, players });
+        }
+    }
+
+    let players = PLAYERS
+        .prefix(round)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .map(|item| item.map(|(addr, tickets)| PlayerTickets { addr, tickets }))
+        .collect::<StdResult<_>>()?;
+    Ok(PlayersResponse { round, players })
+}
+
+pub fn query_player_history(
+    deps: Deps,
+    env: Env,
+    addr: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PlayerHistoryResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let config = CONFIG.load(deps.storage)?;
+    let current_round = ROUND.load(deps.storage)?;
+    // A round is only followed by the next one once its winner claimed.
+    let current_claimed = matches!(
+        LOTTERY_STATE.load(deps.storage)?,
+        LotteryState::CLOSED { claimed: true, .. }
+    );
+
+    let rounds = PARTICIPATIONS
+        .prefix(addr.clone())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (round, participation) = item?;
+            let tickets = if round == current_round {
+                query_ticket_count(deps, env.clone(), addr.clone())?.tickets
+            } else {
+                PLAYERS.may_load(deps.storage, (round, addr.clone()))?
+            };
+            let won = DRAWS
+                .may_load(deps.storage, round)?
+                .is_some_and(|draw| draw.winner == addr);
+            Ok(PlayerRound {
+                round,
+                tickets: tickets.unwrap_or_default(),
+                tickets_bought: participation.tickets_bought,
+                spent: Coin {
+                    denom: config.ticket_unit_cost.denom.clone(),
+                    amount: participation.spent,
+                },
+                won,
+                claimed: won && (round < current_round || current_claimed),
+                refunded: REFUNDS.may_load(deps.storage, (round, addr.clone()))?,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(PlayerHistoryResponse { addr, rounds })
+}
+
+pub fn query_purchases(
+    deps: Deps,
+    round: Option<u64>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PurchasesResponse> {
+    let round = match round {
+        Some(round) => round,
+        None => ROUND.load(deps.storage)?,
+    };
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let purchases = purchases()
+        .idx
+        .round
+        .prefix(round)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, purchase)| purchase))
+        .collect::<StdResult<_>>()?;
+    Ok(PurchasesResponse { round, purchases })
+}
+
+pub fn query_purchase(deps: Deps, id: u64) -> StdResult<PurchaseResponse> {
+    let purchase = purchases().load(deps.storage, id)?;
+    Ok(PurchaseResponse { purchase })
+}
+
+pub fn query_stats(deps: Deps) -> StdResult<StatsResponse> {
+    let stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+    let denom = CONFIG.load(deps.storage)?.ticket_unit_cost.denom;
+    let coin = |amount: Uint128| Coin {
+        denom: denom.clone(),
+        amount,
+    };
+    Ok(StatsResponse {
+        tickets_sold: stats.tickets_sold,
+        volume: coin(stats.volume),
+        fees_paid: coin(stats.fees_paid),
+        largest_jackpot: coin(stats.largest_jackpot),
+        unique_players: stats.unique_players,
+    })
+}
+
+pub fn query_top_winners(deps: Deps, limit: Option<u32>) -> StdResult<TopWinnersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let winners = winnings()
+        .idx
+        .total_won
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, winnings)| winnings))
+        .collect::<StdResult<_>>()?;
+    Ok(TopWinnersResponse { winners })
+}
+
+pub fn query_odds(deps: Deps, env: Env, addr: String) -> StdResult<OddsResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let tickets = query_ticket_count(deps, env.clone(), addr)?
+        .tickets
+        .unwrap_or_default();
+    let lottery_state = query_lottery_state(deps, env)?;
+    Ok(odds(
+        lottery_state.round,
+        tickets,
+        lottery_state.total_tickets,
+    ))
+}
+
+pub fn query_simulate_purchase(
+    deps: Deps,
+    env: Env,
+    addr: String,
+    num_tickets: u64,
+) -> StdResult<SimulatePurchaseResponse> {
+    match LOTTERY_STATE.load(deps.storage)? {
+        LotteryState::OPEN { expiration } if !expiration.is_expired(&env.block) => {}
+        _ => return Err(StdError::generic_err("ticket sales are closed")),
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let cost = config
+        .ticket_unit_cost
+        .amount
+        .checked_mul(Uint128::from(num_tickets))?;
+
+    let current = query_odds(deps, env.clone(), addr)?;
+    let tickets = current
+        .tickets
+        .checked_add(num_tickets)
+        .ok_or_else(|| StdError::generic_err("ticket count overflow"))?;
+    let total_tickets = current
+        .total_tickets
+        .checked_add(num_tickets)
+        .ok_or_else(|| StdError::generic_err("ticket count overflow"))?;
+
+    // Keeper bounties paid later on aren't known yet and come on top of the house fee.
+    let prize_pool = round_prize_pool(deps, &env, &config, current.round)?.checked_add(cost)?;
+    let (_, projected_prize, _) = split_prize_pool(prize_pool, config.house_fee);
+    Ok(SimulatePurchaseResponse {
+        cost: Coin {
+            denom: config.ticket_unit_cost.denom.clone(),
+            amount: cost,
+        },
+        odds: odds(current.round, tickets, total_tickets),
+        projected_prize: Coin {
+            denom: config.ticket_unit_cost.denom,
+            amount: projected_prize,
+        },
+    })
+}
+
+fn odds(round: u64, tickets: u64, total_tickets: u64) -> OddsResponse {
+    OddsResponse {
+        round,
+        tickets,
+        total_tickets,
+        win_probability: if total_tickets == 0 {
+            Decimal::zero()
+        } else {
+            Decimal::from_ratio(tickets, total_tickets)
+        },
+    }
+}
+
+// The ticket token address, if tickets are cw20 and their balances haven't been snapshotted yet.
+fn unsnapshotted_ticket_token(deps: Deps, lottery_state: &LotteryState) -> StdResult<Option<Addr>> {
+    let config = CONFIG.load(deps.storage)?;
+    match (config.ticket_mode, lottery_state) {
+        (TicketMode::Cw20 { .. }, LotteryState::OPEN { .. }) => TICKET_TOKEN.may_load(deps.storage),
+        _ => Ok(None),
+    }
+}
+
+pub fn query_randomness_request(deps: Deps) -> StdResult<RandomnessRequestResponse> {
+    Ok(RandomnessRequestResponse {
+        channel: RANDOMNESS_CHANNEL.may_load(deps.storage)?,
+        status: RANDOMNESS_REQUEST.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_participant_entropy(deps: Deps) -> StdResult<ParticipantEntropyResponse> {
+    Ok(ParticipantEntropyResponse {
+        round: ROUND.load(deps.storage)?,
+        participant_entropy: PARTICIPANT_ENTROPY.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_operator_message(deps: Deps, env: Env) -> StdResult<OperatorMessageResponse> {
+    if let LotteryState::OPEN { .. } = LOTTERY_STATE.load(deps.storage)? {
+        return Err(StdError::generic_err(
+            "the operator message is only fixed once sales close",
+        ));
+    }
+    let round = ROUND.load(deps.storage)?;
+    let ledger_hash = round_ledger_hash(deps.storage)?;
+    let message = operator_message(&env.contract.address, round, &ledger_hash);
+    Ok(OperatorMessageResponse {
+        round,
+        ledger_hash,
+        message: HexBinary::from(message),
+    })
+}
+
+pub fn query_bond(deps: Deps, round: Option<u64>) -> StdResult<BondResponse> {
+    let round = match round {
+        Some(round) => round,
+        None => ROUND.load(deps.storage)?,
+    };
+    Ok(BondResponse {
+        round,
+        bond: BONDS.may_load(deps.storage, round)?,
+    })
+}
+
+pub fn query_payout_preview(deps: Deps, env: Env) -> StdResult<PayoutPreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let round = ROUND.load(deps.storage)?;
+    let denom = config.ticket_unit_cost.denom.clone();
+    let coin = |amount: Uint128| Coin {
+        denom: denom.clone(),
+        amount,
+    };
+
+    // Before the draw, the bounties keepers will take out of the pool first are left out.
+    let prize_pool = round_prize_pool(deps, &env, &config, round)?
+        .saturating_sub(pending_keeper_bounties(deps, &config, round)?);
+    let (house_fee, winner_amount, dust) = split_prize_pool(prize_pool, config.house_fee);
+    let bond = match BONDS.may_load(deps.storage, round)? {
+        Some(Bond {
+            amount,
+            status: BondStatus::Posted {},
+        }) => Some(amount),
+        _ => None,
+    };
+    Ok(PayoutPreviewResponse {
+        round,
+        gross_pool: coin(prize_pool),
+        fees: vec![FeeCut {
+            recipient: config.admin,
+            amount: coin(house_fee),
+        }],
+        winner_amount: coin(winner_amount),
+        dust: coin(dust),
+        bond,
+    })
+}
+
+pub fn query_verify_draw(deps: Deps, round: u64) -> StdResult<VerifyDrawResponse> {
+    let draw = DRAWS.load(deps.storage, round)?;
+    let source_entropy: [u8; 32] = draw.source_entropy.to_array()?;
+    let entropy = mix_entropy(&source_entropy, draw.participant_entropy.as_deref());
+    let winning_ticket = winning_ticket(&entropy, round, draw.total_tickets)
+        .ok_or_else(|| StdError::generic_err("draw has no tickets"))?;
+    Ok(VerifyDrawResponse {
+        verified: draw.version == DRAW_VERSION
+            && entropy == draw.entropy.as_slice()
+            && winning_ticket == draw.winning_ticket,
+        draw,
+        winning_ticket,
+    })
+}
+
+pub fn query_hooks(deps: Deps) -> StdResult<HooksResponse> {
+    let hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(HooksResponse { hooks })
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let ticket_token = TICKET_TOKEN.may_load(deps.storage)?;
+    Ok(ConfigResponse {
+        config,
+        ticket_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        attr, coin, coins, Addr, BankMsg, Decimal, Event, HexBinary, OwnedDeps, SubMsg, Uint128,
+    };
+    use k256::ecdsa::{Signature, SigningKey};
+    use k256::elliptic_curve::ops::Reduce;
+    use k256::elliptic_curve::{AffineXCoordinate, PrimeField};
+    use k256::{ProjectivePoint, Scalar, U256};
+    use sha2::{Digest, Sha256};
+
+    use crate::constants::DEFAULT_DRAW_DEADLINE_SECONDS;
+    use crate::contract::{
+        append_ticket_range, execute, instantiate, query_odds, query_operator_message,
+        query_participant_entropy, query_payout_preview, query_player_history, query_players,
+        query_purchase, query_purchases, query_simulate_purchase, query_stats, query_status,
+        query_ticket_count, query_top_winners, query_verify_draw, ticket_owner,
+    };
+    use crate::msg::ExecuteMsg;
+    use crate::msg::InstantiateMsg;
+    use crate::msg::ParticipantEntropyResponse;
+    use crate::msg::{
+        FeeCut, OddsResponse, PayoutPreviewResponse, Phase, PlayerRound, PurchasesResponse,
+        SimulatePurchaseResponse, StatsResponse,
+    };
+    use crate::state::{
+        KeeperBounty, OperatorSignatureConfig, ParticipantEntropy, Purchase, RandomnessSource,
+        SignatureScheme, Winnings, PLAYERS, ROUND, TOTAL_TICKETS,
+    };
+    use crate::test_util::tests::{
+        testing_instantiate_msg, testing_salt, testing_seed_commitment, TestUser,
+        TESTING_1_WEEK_IN_SECONDS, TESTING_DURATION, TESTING_NATIVE_DENOM, TESTING_SEED,
+        TESTING_TICKET_COST, TEST_ADMIN, TEST_USER_1, TEST_USER_2, TEST_USER_3,
+    };
+    use crate::util::{accumulate_entropy, mix_entropy, seed_entropy, winning_ticket};
+    use crate::ContractError;
+
+    #[test]
+    fn proper_initialization() {
+        let instantiate_message = testing_instantiate_msg();
+
+        let mut deps = mock_dependencies();
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let res = instantiate(deps.as_mut(), mock_env(), info, instantiate_message).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn buy_tickets() {
+        let instantiate_message = testing_instantiate_msg();
+
+        let mut deps = mock_dependencies();
+
+        let _ = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(1000, "earth")),
+            instantiate_message,
+        )
+        .unwrap();
+
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(1000, TESTING_NATIVE_DENOM)),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let res = query_ticket_count(
+            deps.as_ref(),
+            mock_env(),
+            Addr::unchecked("creator".to_string()),
+        );
+
+        assert!(res.is_ok());
+        let ticket_response = res.unwrap();
+        assert_eq!(ticket_response.tickets, Some(1))
+    }
+
+    #[test]
+    fn buy_multiple_tickets() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_message = testing_instantiate_msg();
+
+        let test_users = vec![
+            TestUser {
+                addr: "creator".to_string(),
+                tickets: 1,
+                coin: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            },
+            TestUser {
+                addr: "testUserA".to_string(),
+                tickets: 10,
+                coin: coin(TESTING_TICKET_COST * 10, TESTING_NATIVE_DENOM),
+            },
+            TestUser {
+                addr: "testUserB".to_string(),
+                tickets: 10,
+                coin: coin(TESTING_TICKET_COST * 10, TESTING_NATIVE_DENOM),
+            },
+        ];
+
+        let _ = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(1000, "earth")),
+            instantiate_message,
+        )
+        .unwrap();
+
+        for test_user in test_users {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(&test_user.addr, &[test_user.coin]),
+                ExecuteMsg::BuyTicket {
+                    num_tickets: test_user.tickets,
+                    recipient: None,
+                    entropy: None,
+                },
+            )
+            .unwrap();
+
+            let res =
+                query_ticket_count(deps.as_ref(), mock_env(), Addr::unchecked(test_user.addr));
+            assert!(res.is_ok());
+
+            let ticket_response = res.unwrap();
+            assert_eq!(ticket_response.tickets, Some(test_user.tickets));
+        }
+    }
+
+    #[test]
+    fn gift_and_transfer_tickets() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_message = testing_instantiate_msg();
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_message,
+        )
+        .unwrap();
+
+        // User 1 pays for 5 tickets but they all go to user 2.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                TEST_USER_1,
+                &coins(5 * TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 5,
+                recipient: Some(TEST_USER_2.to_string()),
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let ticket_count = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, addr: &str| {
+            query_ticket_count(deps.as_ref(), mock_env(), Addr::unchecked(addr))
+                .unwrap()
+                .tickets
+        };
+        assert_eq!(ticket_count(&deps, TEST_USER_1), None);
+        assert_eq!(ticket_count(&deps, TEST_USER_2), Some(5));
+
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(TEST_USER_2, &[]),
+                ExecuteMsg::TransferTickets {
+                    recipient: TEST_USER_3.to_string(),
+                    amount: 6,
+                },
+            )
+            .unwrap_err(),
+            ContractError::TicketTransferInsufficientTickets {}
+        );
+
+        // Empty transfers would make the recipient a player without tickets.
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(TEST_USER_3, &[]),
+                ExecuteMsg::TransferTickets {
+                    recipient: TEST_USER_1.to_string(),
+                    amount: 0,
+                },
+            )
+            .unwrap_err(),
+            ContractError::TicketTransferZeroAmount {}
+        );
+        assert_eq!(ticket_count(&deps, TEST_USER_1), None);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_USER_2, &[]),
+            ExecuteMsg::TransferTickets {
+                recipient: TEST_USER_3.to_string(),
+                amount: 2,
+            },
+        )
+        .unwrap();
+        assert_eq!(ticket_count(&deps, TEST_USER_2), Some(3));
+        assert_eq!(ticket_count(&deps, TEST_USER_3), Some(2));
+
+        // Moving every ticket away removes the holder entirely.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_USER_3, &[]),
+            ExecuteMsg::TransferTickets {
+                recipient: TEST_USER_2.to_string(),
+                amount: 2,
+            },
+        )
+        .unwrap();
+        assert_eq!(ticket_count(&deps, TEST_USER_2), Some(5));
+        assert_eq!(ticket_count(&deps, TEST_USER_3), None);
+
+        // Once the sales window is over transfers are rejected, even before the state moves on.
+        let mut expired_env = mock_env();
+        expired_env.block.time = expired_env
+            .block
+            .time
+            .plus_seconds(TESTING_1_WEEK_IN_SECONDS + 1);
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                expired_env,
+                mock_info(TEST_USER_2, &[]),
+                ExecuteMsg::TransferTickets {
+                    recipient: TEST_USER_3.to_string(),
+                    amount: 1,
+                },
+            )
+            .unwrap_err(),
+            ContractError::TicketTransferNotAvailable {}
+        );
+    }
+
+    #[test]
+    fn execute_lottery_requires_matching_seed_reveal() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_message = testing_instantiate_msg();
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_message,
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let mut expired_env = mock_env();
+        expired_env.block.time = expired_env
+            .block
+            .time
+            .plus_seconds(TESTING_1_WEEK_IN_SECONDS + 1);
+        execute(
+            deps.as_mut(),
+            expired_env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+
+        // A different seed, or the right seed with another salt, doesn't open the commitment.
+        for (seed, salt) in [
+            (TESTING_SEED + 1, testing_salt()),
+            (TESTING_SEED, HexBinary::from(b"other salt")),
+        ] {
+            assert_eq!(
+                execute(
+                    deps.as_mut(),
+                    expired_env.clone(),
+                    mock_info(TEST_ADMIN, &[]),
+                    ExecuteMsg::ExecuteLottery { seed, salt },
+                )
+                .unwrap_err(),
+                ContractError::SeedCommitmentMismatch {}
+            );
+        }
+
+        execute(
+            deps.as_mut(),
+            expired_env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+        )
+        .unwrap();
+
+        // The winner hasn't claimed yet so the next round can't be started.
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                expired_env,
+                mock_info(TEST_ADMIN, &[]),
+                ExecuteMsg::StartRound {
+                    seed_commitment: Some(testing_seed_commitment()),
+                },
+            )
+            .unwrap_err(),
+            ContractError::RoundNotFinished {}
+        );
+    }
+
+    #[test]
+    fn draws_are_reproducible_from_stored_inputs() {
+        // Pinned outputs of the v1 derivation, a change here breaks verification of past draws.
+        assert_eq!(winning_ticket(&[0u8; 32], 1, 5), Some(2));
+        assert_eq!(winning_ticket(&[0xffu8; 32], 7, 1_000_000), Some(876_689));
+        assert_eq!(winning_ticket(&[0u8; 32], 1, 0), None);
+
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_message,
+        )
+        .unwrap();
+
+        // Only the first buyer contributes entropy.
+        for (user, num_tickets, entropy) in [
+            (TEST_USER_1, 3, Some(HexBinary::from(b"lucky"))),
+            (TEST_USER_2, 2, None),
+        ] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(
+                    user,
+                    &coins(
+                        TESTING_TICKET_COST * num_tickets as u128,
+                        TESTING_NATIVE_DENOM,
+                    ),
+                ),
+                ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient: None,
+                    entropy,
+                },
+            )
+            .unwrap();
+        }
+        let participant_entropy = accumulate_entropy(None, &Addr::unchecked(TEST_USER_1), b"lucky");
+        assert_eq!(
+            query_participant_entropy(deps.as_ref()).unwrap(),
+            ParticipantEntropyResponse {
+                round: 1,
+                participant_entropy: Some(ParticipantEntropy {
+                    accumulator: participant_entropy.clone(),
+                    contributions: 1,
+                }),
+            }
+        );
+
+        let mut expired_env = mock_env();
+        expired_env.block.time = expired_env
+            .block
+            .time
+            .plus_seconds(TESTING_1_WEEK_IN_SECONDS + 1);
+        execute(
+            deps.as_mut(),
+            expired_env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            expired_env,
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+        )
+        .unwrap();
+
+        let verification = query_verify_draw(deps.as_ref(), 1).unwrap();
+        assert!(verification.verified);
+        assert_eq!(verification.draw.version, 1);
+        let source_entropy = seed_entropy(TESTING_SEED, &testing_salt());
+        assert_eq!(
+            verification.draw.source_entropy,
+            HexBinary::from(source_entropy)
+        );
+        assert_eq!(
+            verification.draw.participant_entropy,
+            Some(participant_entropy.clone())
+        );
+        assert_eq!(
+            verification.draw.entropy,
+            HexBinary::from(mix_entropy(&source_entropy, Some(&participant_entropy)))
+        );
+        assert_eq!(verification.draw.total_tickets, 5);
+        assert_eq!(
+            verification.draw.winning_ticket,
+            verification.winning_ticket
+        );
+        assert!(query_verify_draw(deps.as_ref(), 2).is_err());
+    }
+
+    #[test]
+    fn buyer_entropy_changes_the_winner_of_the_same_seed() {
+        // Both buyers contribute: the query returns the chain over their contributions.
+        let (_, participant_entropy) =
+            draw_with_entropy([Some(b"lucky".as_slice()), Some(b"charm".as_slice())]);
+        let first = Sha256::new()
+            .chain_update([0u8; 32])
+            .chain_update(TEST_USER_1)
+            .chain_update(b"lucky")
+            .finalize();
+        let accumulator = Sha256::new()
+            .chain_update(first)
+            .chain_update(TEST_USER_2)
+            .chain_update(b"charm")
+            .finalize();
+        assert_eq!(
+            participant_entropy,
+            Some(ParticipantEntropy {
+                accumulator: HexBinary::from(accumulator.as_slice()),
+                contributions: 2,
+            })
+        );
+
+        // The admin's seed and salt stay the same, only user1's entropy changes.
+        let (winner, participant_entropy) = draw_with_entropy([None, None]);
+        assert_eq!(participant_entropy, None);
+        let total_tickets = 2;
+        let unmixed = winning_ticket(
+            &mix_entropy(&seed_entropy(TESTING_SEED, &testing_salt()), None),
+            1,
+            total_tickets,
+        )
+        .unwrap();
+        assert_eq!(winner, [TEST_USER_1, TEST_USER_2][unmixed as usize]);
+        let winners: Vec<Addr> = [b"0", b"1", b"2", b"3", b"4", b"5", b"6", b"7"]
+            .into_iter()
+            .map(|entropy| draw_with_entropy([Some(entropy.as_slice()), None]).0)
+            .collect();
+        assert!(winners.contains(&Addr::unchecked(TEST_USER_1)));
+        assert!(winners.contains(&Addr::unchecked(TEST_USER_2)));
+    }
+
+    // Runs a round in which user1 and user2 buy a ticket each with the given entropy and the
+    // admin draws with the testing seed. Returns the winner and the round's participant entropy.
+    fn draw_with_entropy(entropy: [Option<&[u8]>; 2]) -> (Addr, Option<ParticipantEntropy>) {
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        for (user, entropy) in [TEST_USER_1, TEST_USER_2].into_iter().zip(entropy) {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(user, &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)),
+                ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: entropy.map(HexBinary::from),
+                },
+            )
+            .unwrap();
+        }
+        let participant_entropy = query_participant_entropy(deps.as_ref())
+            .unwrap()
+            .participant_entropy;
+
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS + 1);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+        )
+        .unwrap();
+        let winner = query_verify_draw(deps.as_ref(), 1).unwrap().draw.winner;
+        (winner, participant_entropy)
+    }
+
+    // user1, user2 and user3 bought 1, 2 and 7 of the round's 10 tickets, in that order.
+    fn setup_players(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) -> Vec<(Addr, u64)> {
+        let players = vec![
+            (Addr::unchecked(TEST_USER_1), 1),
+            (Addr::unchecked(TEST_USER_2), 2),
+            (Addr::unchecked(TEST_USER_3), 7),
+        ];
+        for (player, tickets) in &players {
+            append_ticket_range(deps.as_mut().storage, 1, player, *tickets).unwrap();
+            PLAYERS
+                .save(deps.as_mut().storage, (1, player.clone()), tickets)
+                .unwrap();
+        }
+        players
+    }
+
+    fn owners(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, tickets: u64) -> Vec<Addr> {
+        (0..tickets)
+            .map(|ticket| {
+                ticket_owner(deps.as_ref().storage, 1, ticket)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn players_are_listed_in_pages() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        let players = setup_players(&mut deps);
+
+        let first_page = query_players(deps.as_ref(), None, None, Some(2)).unwrap();
+        assert_eq!(first_page.round, 1);
+        let second_page = query_players(
+            deps.as_ref(),
+            Some(1),
+            Some(first_page.players[1].addr.to_string()),
+            Some(2),
+        )
+        .unwrap();
+        let listed: Vec<(Addr, u64)> = first_page
+            .players
+            .into_iter()
+            .chain(second_page.players)
+            .map(|player| (player.addr, player.tickets))
+            .collect();
+        assert_eq!(listed, players);
+
+        // Other rounds have their own players.
+        assert_eq!(
+            query_players(deps.as_ref(), Some(2), None, None)
+                .unwrap()
+                .players,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn odds_and_simulated_purchases() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        for (user, num_tickets) in [(TEST_USER_1, 1u64), (TEST_USER_2, 2)] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(
+                    user,
+                    &coins(
+                        TESTING_TICKET_COST * u128::from(num_tickets),
+                        TESTING_NATIVE_DENOM,
+                    ),
+                ),
+                ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient: None,
+                    entropy: None,
+                },
+            )
+            .unwrap();
+        }
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(3_000, TESTING_NATIVE_DENOM));
+
+        assert_eq!(
+            query_odds(deps.as_ref(), mock_env(), TEST_USER_1.to_string()).unwrap(),
+            OddsResponse {
+                round: 1,
+                tickets: 1,
+                total_tickets: 3,
+                win_probability: Decimal::from_ratio(1u64, 3u64),
+            }
+        );
+        assert_eq!(
+            query_odds(deps.as_ref(), mock_env(), TEST_USER_3.to_string())
+                .unwrap()
+                .win_probability,
+            Decimal::zero()
+        );
+
+        // Two more tickets make it 3 out of 5, and the 5000 pool pays 4750 after the 5% fee.
+        assert_eq!(
+            query_simulate_purchase(deps.as_ref(), mock_env(), TEST_USER_1.to_string(), 2).unwrap(),
+            SimulatePurchaseResponse {
+                cost: coin(2_000, TESTING_NATIVE_DENOM),
+                odds: OddsResponse {
+                    round: 1,
+                    tickets: 3,
+                    total_tickets: 5,
+                    win_probability: Decimal::percent(60),
+                },
+                projected_prize: coin(4_750, TESTING_NATIVE_DENOM),
+            }
+        );
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        assert!(query_simulate_purchase(deps.as_ref(), env, TEST_USER_1.to_string(), 2).is_err());
+    }
+
+    #[test]
+    fn status_follows_the_block_before_the_state_does() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let status = query_status(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(status.phase, Phase::Open {});
+        assert_eq!(status.seconds_remaining, Some(TESTING_1_WEEK_IN_SECONDS));
+        assert_eq!(status.valid_actions, vec!["buy_ticket", "transfer_tickets"]);
+
+        // The stored state is still OPEN, but sales are over.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        let status = query_status(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(status.phase, Phase::SalesEnded {});
+        assert_eq!(status.seconds_remaining, None);
+        assert_eq!(status.valid_actions, vec!["close_sales"]);
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        let status = query_status(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(status.phase, Phase::Choosing {});
+        assert_eq!(
+            status.seconds_remaining,
+            Some(DEFAULT_DRAW_DEADLINE_SECONDS)
+        );
+        assert!(status.draw_executable);
+        assert_eq!(status.valid_actions, vec!["execute_lottery"]);
+
+        env.block.time = env.block.time.plus_seconds(DEFAULT_DRAW_DEADLINE_SECONDS);
+        let status = query_status(deps.as_ref(), env).unwrap();
+        assert_eq!(status.phase, Phase::DrawExpired {});
+        assert!(!status.draw_executable);
+        assert_eq!(status.valid_actions, vec!["expire_draw"]);
+    }
+
+    #[test]
+    fn player_history_lists_every_round_entered() {
+        let mut deps = mock_dependencies();
         let instantiate_message = InstantiateMsg {
-            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
-            lottery_duration: TESTING_DURATION,
-            admin: TEST_ADMIN.to_string(),
-            house_fee: 500,
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
         };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
 
-        let test_users = vec![
-            TestUser {
-                addr: "creator".to_string(),
-                tickets: 1,
-                coin: coin(TESTING_TICKET_COST * 1, TESTING_NATIVE_DENOM),
+        // user1 buys 2 tickets for themselves and gifts 1 to user2.
+        for recipient in [None, Some(TEST_USER_2.to_string()), None] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(
+                    TEST_USER_1,
+                    &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+                ),
+                ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient,
+                    entropy: None,
+                },
+            )
+            .unwrap();
+        }
+
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
             },
-            TestUser {
-                addr: "testUserA".to_string(),
-                tickets: 10,
-                coin: coin(TESTING_TICKET_COST * 10, TESTING_NATIVE_DENOM),
+        )
+        .unwrap();
+        let winner = query_verify_draw(deps.as_ref(), 1).unwrap().draw.winner;
+
+        let history = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, addr: &str| {
+            query_player_history(deps.as_ref(), mock_env(), addr.to_string(), None, None)
+                .unwrap()
+                .rounds
+        };
+        let user1_round = PlayerRound {
+            round: 1,
+            tickets: 2,
+            tickets_bought: 3,
+            spent: coin(3_000, TESTING_NATIVE_DENOM),
+            won: winner == TEST_USER_1,
+            claimed: false,
+            refunded: None,
+        };
+        let user2_round = PlayerRound {
+            round: 1,
+            tickets: 1,
+            tickets_bought: 0,
+            spent: coin(0, TESTING_NATIVE_DENOM),
+            won: winner == TEST_USER_2,
+            claimed: false,
+            refunded: None,
+        };
+        assert_eq!(history(&deps, TEST_USER_1), vec![user1_round.clone()]);
+        assert_eq!(history(&deps, TEST_USER_2), vec![user2_round.clone()]);
+        assert_eq!(history(&deps, TEST_USER_3), vec![]);
+
+        // Once claimed, the next round starts and shows up after the first one.
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(winner.as_str(), &[]),
+            ExecuteMsg::ClaimTokens {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::StartRound {
+                seed_commitment: None,
             },
-            TestUser {
-                addr: "testUserB".to_string(),
-                tickets: 10,
-                coin: coin(TESTING_TICKET_COST * 10, TESTING_NATIVE_DENOM),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let user1_history = history(&deps, TEST_USER_1);
+        assert_eq!(user1_history.len(), 2);
+        assert_eq!(user1_history[0].claimed, user1_round.won);
+        assert_eq!(
+            user1_history[1],
+            PlayerRound {
+                round: 2,
+                tickets: 1,
+                tickets_bought: 1,
+                spent: coin(1_000, TESTING_NATIVE_DENOM),
+                won: false,
+                claimed: false,
+                refunded: None,
+            }
+        );
+        let after_first: Vec<u64> = query_player_history(
+            deps.as_ref(),
+            mock_env(),
+            TEST_USER_1.to_string(),
+            Some(1),
+            None,
+        )
+        .unwrap()
+        .rounds
+        .into_iter()
+        .map(|round| round.round)
+        .collect();
+        assert_eq!(after_first, vec![2]);
+    }
+
+    #[test]
+    fn stats_and_top_winners_add_up_over_rounds() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+
+        let mut winners = vec![];
+        for round_buys in [
+            vec![(TEST_USER_1, 2), (TEST_USER_2, 1)],
+            vec![(TEST_USER_2, 1), (TEST_USER_3, 3)],
+        ] {
+            let mut pool = 0;
+            for (buyer, num_tickets) in round_buys {
+                let cost = TESTING_TICKET_COST * u128::from(num_tickets);
+                pool += cost;
+                execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    mock_info(buyer, &coins(cost, TESTING_NATIVE_DENOM)),
+                    ExecuteMsg::BuyTicket {
+                        num_tickets,
+                        recipient: None,
+                        entropy: None,
+                    },
+                )
+                .unwrap();
+            }
+            deps.querier
+                .update_balance(MOCK_CONTRACT_ADDR, coins(pool, TESTING_NATIVE_DENOM));
+
+            env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(TEST_USER_1, &[]),
+                ExecuteMsg::CloseSales {},
+            )
+            .unwrap();
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(TEST_ADMIN, &[]),
+                ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+            )
+            .unwrap();
+            let round = ROUND.load(&deps.storage).unwrap();
+            let winner = query_verify_draw(deps.as_ref(), round).unwrap().draw.winner;
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(winner.as_str(), &[]),
+                ExecuteMsg::ClaimTokens {},
+            )
+            .unwrap();
+            winners.push((winner, pool - pool * 5 / 100));
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(TEST_ADMIN, &[]),
+                ExecuteMsg::StartRound {
+                    seed_commitment: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // 3_000 then 4_000 were played, with a 5% house fee.
+        assert_eq!(
+            query_stats(deps.as_ref()).unwrap(),
+            StatsResponse {
+                tickets_sold: 7,
+                volume: coin(7_000, TESTING_NATIVE_DENOM),
+                fees_paid: coin(350, TESTING_NATIVE_DENOM),
+                largest_jackpot: coin(3_800, TESTING_NATIVE_DENOM),
+                unique_players: 3,
+            }
+        );
+
+        let mut expected: Vec<Winnings> = vec![];
+        for (addr, won) in winners {
+            match expected.iter_mut().find(|winnings| winnings.addr == addr) {
+                Some(winnings) => {
+                    winnings.total_won += Uint128::new(won);
+                    winnings.wins += 1;
+                }
+                None => expected.push(Winnings {
+                    addr,
+                    total_won: Uint128::new(won),
+                    wins: 1,
+                }),
+            }
+        }
+        expected.sort_by_key(|winnings| std::cmp::Reverse(winnings.total_won));
+        assert_eq!(
+            query_top_winners(deps.as_ref(), None).unwrap().winners,
+            expected
+        );
+        assert_eq!(
+            query_top_winners(deps.as_ref(), Some(1)).unwrap().winners,
+            expected[..1]
+        );
+    }
+
+    #[test]
+    fn purchases_are_recorded_in_a_ledger() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+
+        // user1 buys 2 tickets, then gifts 3 to user2 a block later.
+        for (num_tickets, recipient) in [(2, None), (3, Some(TEST_USER_2.to_string()))] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(
+                    TEST_USER_1,
+                    &coins(
+                        TESTING_TICKET_COST * u128::from(num_tickets),
+                        TESTING_NATIVE_DENOM,
+                    ),
+                ),
+                ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient,
+                    entropy: None,
+                },
+            )
+            .unwrap();
+            env.block.height += 1;
+            env.block.time = env.block.time.plus_seconds(5);
+        }
+
+        let start = mock_env().block;
+        let purchases = vec![
+            Purchase {
+                id: 1,
+                round: 1,
+                buyer: Addr::unchecked(TEST_USER_1),
+                recipient: Addr::unchecked(TEST_USER_1),
+                tickets: 2,
+                paid: coin(2_000, TESTING_NATIVE_DENOM),
+                height: start.height,
+                time: start.time,
+            },
+            Purchase {
+                id: 2,
+                round: 1,
+                buyer: Addr::unchecked(TEST_USER_1),
+                recipient: Addr::unchecked(TEST_USER_2),
+                tickets: 3,
+                paid: coin(3_000, TESTING_NATIVE_DENOM),
+                height: start.height + 1,
+                time: start.time.plus_seconds(5),
             },
         ];
+        assert_eq!(
+            query_purchases(deps.as_ref(), None, None, None).unwrap(),
+            PurchasesResponse {
+                round: 1,
+                purchases: purchases.clone(),
+            }
+        );
+        assert_eq!(
+            query_purchases(deps.as_ref(), Some(1), Some(1), None)
+                .unwrap()
+                .purchases,
+            purchases[1..]
+        );
+        assert_eq!(
+            query_purchases(deps.as_ref(), Some(2), None, None)
+                .unwrap()
+                .purchases,
+            vec![]
+        );
+        assert_eq!(
+            query_purchase(deps.as_ref(), 2).unwrap().purchase,
+            purchases[1]
+        );
+        query_purchase(deps.as_ref(), 3).unwrap_err();
+    }
 
-        let _ = instantiate(
+    #[test]
+    fn events_describe_every_step_of_a_round() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = InstantiateMsg {
+            keeper_bounty: Some(KeeperBounty::Fixed(Uint128::new(100))),
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let mut env = mock_env();
+        let res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, "config_change");
+        assert_eq!(attribute(&res.events[0], "round"), "1");
+        assert!(attribute(&res.events[0], "config").contains("\"admin_seed\""));
+        assert_eq!(
+            res.events[1],
+            Event::new("round_started")
+                .add_attribute("round", "1")
+                .add_attribute("sales_end", TESTING_DURATION.after(&env.block).to_string())
+        );
+
+        // user1 buys 2 tickets, then gifts 3 to user2.
+        let mut purchase_events = vec![];
+        for (num_tickets, recipient) in [(2, None), (3, Some(TEST_USER_2.to_string()))] {
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(
+                    TEST_USER_1,
+                    &coins(
+                        TESTING_TICKET_COST * u128::from(num_tickets),
+                        TESTING_NATIVE_DENOM,
+                    ),
+                ),
+                ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient,
+                    entropy: None,
+                },
+            )
+            .unwrap();
+            purchase_events.push(res.events[0].clone());
+        }
+        assert_eq!(
+            purchase_events[1],
+            Event::new("purchase")
+                .add_attribute("round", "1")
+                .add_attribute("purchase_id", "2")
+                .add_attribute("buyer", TEST_USER_1)
+                .add_attribute("recipient", TEST_USER_2)
+                .add_attribute("tickets", "3")
+                .add_attribute("paid", "3000ulotto")
+                .add_attribute("ticket_start", "2")
+                .add_attribute("ticket_end", "5")
+        );
+        assert_eq!(attribute(&purchase_events[0], "ticket_end"), "2");
+
+        // user2 passes on the top 2 of their tickets.
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_2, &[]),
+            ExecuteMsg::TransferTickets {
+                recipient: TEST_USER_3.to_string(),
+                amount: 2,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("ticket_transfer")
+                .add_attribute("round", "1")
+                .add_attribute("sender", TEST_USER_2)
+                .add_attribute("recipient", TEST_USER_3)
+                .add_attribute("tickets", "2")
+                .add_attribute("ticket_start", "3")
+                .add_attribute("ticket_end", "5")]
+        );
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(5_000, TESTING_NATIVE_DENOM));
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_3, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, "sales_closed");
+        assert_eq!(attribute(&res.events[0], "total_tickets"), "5");
+        assert_eq!(
+            res.events[1],
+            Event::new("keeper_bounty")
+                .add_attribute("round", "1")
+                .add_attribute("keeper", TEST_USER_3)
+                .add_attribute("bounty", "100ulotto")
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+        )
+        .unwrap();
+        let draw = query_verify_draw(deps.as_ref(), 1).unwrap().draw;
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("method", "execute_lottery"),
+                attr("winner", draw.winner.as_str()),
+            ]
+        );
+        assert_eq!(res.events[0].ty, "keeper_bounty");
+        assert_eq!(res.events[1].ty, "draw");
+        assert_eq!(attribute(&res.events[1], "winner"), draw.winner.as_str());
+        assert_eq!(
+            attribute(&res.events[1], "winning_ticket"),
+            draw.winning_ticket.to_string()
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info(draw.winner.as_str(), &[]),
+            ExecuteMsg::ClaimTokens {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("claim")
+                .add_attribute("round", "1")
+                .add_attribute("winner", draw.winner.as_str())
+                .add_attribute("prize", "4750ulotto")
+                .add_attribute("gross_pool", "5000ulotto")
+                .add_attribute("fee_recipient", TEST_ADMIN)
+                .add_attribute("fee", "250ulotto")]
+        );
+    }
+
+    fn attribute(event: &Event, key: &str) -> String {
+        event
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.clone())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn operator_signatures_have_to_be_low_s() {
+        let operator_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let operator_pubkey = operator_key.verifying_key().to_bytes();
+        // ECDSA with a chosen nonce k. r only depends on k, so the operator can commit to it
+        // before the message is known.
+        let secret = Scalar::from_repr([7u8; 32].into()).unwrap();
+        let nonce = Scalar::from_repr([9u8; 32].into()).unwrap();
+        let r = <Scalar as Reduce<U256>>::from_be_bytes_reduced(
+            (ProjectivePoint::GENERATOR * nonce).to_affine().x(),
+        );
+        let sign = |message| {
+            let z = <Scalar as Reduce<U256>>::from_be_bytes_reduced(message);
+            let signature =
+                Signature::from_scalars(r, nonce.invert().unwrap() * (z + r * secret)).unwrap();
+            signature.normalize_s().unwrap_or(signature)
+        };
+        let nonce_commitment = r.to_bytes();
+        let mut deps = mock_dependencies();
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::OperatorSignature(
+                OperatorSignatureConfig {
+                    scheme: SignatureScheme::Secp256k1 {},
+                    pubkey: HexBinary::from(operator_pubkey.as_slice()),
+                },
+            )),
+            seed_commitment: Some(HexBinary::from(nonce_commitment.as_slice())),
+            ..testing_instantiate_msg()
+        };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            instantiate_message,
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+
+        // The operator message is itself a sha256 digest, signed as the prehashed message.
+        let ledger_hash = query_operator_message(deps.as_ref(), env.clone())
+            .unwrap()
+            .ledger_hash;
+        let mut preimage = Sha256::new();
+        preimage.update(b"sphinx-operator");
+        preimage.update(env.contract.address.as_bytes());
+        preimage.update(1u64.to_be_bytes());
+        preimage.update(ledger_hash.as_slice());
+        let signature: Signature = sign(preimage.finalize());
+        let high_s = Signature::from_scalars(signature.r(), -*signature.s()).unwrap();
+
+        // Both verify, but only the low-S encoding is taken as the draw's entropy.
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(TEST_USER_2, &[]),
+                ExecuteMsg::SubmitOperatorSignature {
+                    signature: HexBinary::from(high_s.as_ref()),
+                },
+            )
+            .unwrap_err(),
+            ContractError::InvalidOperatorSignature {}
+        );
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info(TEST_USER_2, &[]),
+            ExecuteMsg::SubmitOperatorSignature {
+                signature: HexBinary::from(signature.as_ref()),
+            },
+        )
+        .unwrap();
+        assert!(query_verify_draw(deps.as_ref(), 1).unwrap().verified);
+    }
+
+    #[test]
+    fn claims_pay_out_the_payout_preview() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = InstantiateMsg {
+            bond: Some(coin(2_000, TESTING_NATIVE_DENOM)),
+            keeper_bounty: Some(KeeperBounty::PoolShare(Decimal::percent(1))),
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &coins(2_000, TESTING_NATIVE_DENOM)),
+            instantiate_message,
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST * 5, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 5,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        // The keepers take 1% of what's left of the pool on each call: 50, then 49. The preview
+        // leaves them out from the start, it doesn't change as they get paid.
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(7_000, TESTING_NATIVE_DENOM));
+        let preview = query_payout_preview(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(
+            preview,
+            PayoutPreviewResponse {
+                round: 1,
+                gross_pool: coin(4_901, TESTING_NATIVE_DENOM),
+                fees: vec![FeeCut {
+                    recipient: Addr::unchecked(TEST_ADMIN),
+                    amount: coin(245, TESTING_NATIVE_DENOM),
+                }],
+                winner_amount: coin(4_655, TESTING_NATIVE_DENOM),
+                dust: coin(1, TESTING_NATIVE_DENOM),
+                bond: Some(coin(2_000, TESTING_NATIVE_DENOM)),
+            }
+        );
+        env.block.time = env.block.time.plus_seconds(TESTING_1_WEEK_IN_SECONDS);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_USER_3, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+        assert_eq!(attribute(&res.events[1], "bounty"), "50ulotto");
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(6_950, TESTING_NATIVE_DENOM));
+        assert_eq!(
+            query_payout_preview(deps.as_ref(), env.clone()).unwrap(),
+            preview
+        );
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_ADMIN, &[]),
+            ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+        )
+        .unwrap();
+        assert_eq!(attribute(&res.events[0], "bounty"), "49ulotto");
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(6_901, TESTING_NATIVE_DENOM));
+        assert_eq!(
+            query_payout_preview(deps.as_ref(), env.clone()).unwrap(),
+            preview
+        );
+
+        // The claim pays out the preview taken before the draw, the dust stays behind.
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::ClaimTokens {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: TEST_USER_1.to_string(),
+                    amount: vec![preview.winner_amount],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: TEST_ADMIN.to_string(),
+                    amount: vec![preview.fees[0].amount.clone()],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: TEST_ADMIN.to_string(),
+                    amount: vec![preview.bond.unwrap()],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_ticket_belongs_to_exactly_one_player() {
+        let mut deps = mock_dependencies();
+        let players = setup_players(&mut deps);
+        let owners = owners(&deps, 10);
+
+        for (player, tickets) in players {
+            let owned = owners.iter().filter(|owner| **owner == player).count();
+            assert_eq!(owned as u64, tickets);
+        }
+        assert_eq!(ticket_owner(deps.as_ref().storage, 1, 10).unwrap(), None);
+        assert_eq!(TOTAL_TICKETS.load(deps.as_ref().storage, 1).unwrap(), 10);
+    }
+
+    #[test]
+    fn transfers_split_ticket_ranges() {
+        let mut deps = mock_dependencies();
+        let instantiate_message = testing_instantiate_msg();
+        instantiate(
             deps.as_mut(),
             mock_env(),
-            mock_info("creator", &coins(1000, "earth")),
+            mock_info("creator", &[]),
             instantiate_message,
         )
         .unwrap();
 
-        for test_user in test_users {
+        for (user, num_tickets) in [(TEST_USER_1, 3), (TEST_USER_2, 2), (TEST_USER_1, 4)] {
             execute(
                 deps.as_mut(),
                 mock_env(),
-                mock_info(&test_user.addr, &[test_user.coin]),
+                mock_info(
+                    user,
+                    &coins(
+                        TESTING_TICKET_COST * num_tickets as u128,
+                        TESTING_NATIVE_DENOM,
+                    ),
+                ),
                 ExecuteMsg::BuyTicket {
-                    num_tickets: test_user.tickets,
+                    num_tickets,
+                    recipient: None,
+                    entropy: None,
                 },
             )
             .unwrap();
+        }
 
-            let res =
-                query_ticket_count(deps.as_ref(), mock_env(), Addr::unchecked(test_user.addr));
-            assert!(res.is_ok());
+        // user1's latest range moves as a whole, the one before it is split.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::TransferTickets {
+                recipient: TEST_USER_3.to_string(),
+                amount: 5,
+            },
+        )
+        .unwrap();
 
-            let ticket_response = res.unwrap();
-            assert_eq!(ticket_response.tickets, Some(test_user.tickets));
+        let (user_1, user_2, user_3) = (
+            Addr::unchecked(TEST_USER_1),
+            Addr::unchecked(TEST_USER_2),
+            Addr::unchecked(TEST_USER_3),
+        );
+        assert_eq!(
+            owners(&deps, 9),
+            vec![
+                user_1.clone(),
+                user_1,
+                user_3.clone(),
+                user_2.clone(),
+                user_2,
+                user_3.clone(),
+                user_3.clone(),
+                user_3.clone(),
+                user_3
+            ]
+        );
+        assert_eq!(ticket_owner(deps.as_ref().storage, 1, 9).unwrap(), None);
+    }
+
+    #[test]
+    fn winners_are_drawn_in_proportion_to_their_tickets() {
+        let mut deps = mock_dependencies();
+        let players = setup_players(&mut deps);
+        let owners = owners(&deps, 10);
+
+        let draws = 20_000u64;
+        let mut ticket_counts = [0u64; 10];
+        for seed in 0..draws {
+            let ticket = winning_ticket(&seed_entropy(seed, b"stats"), 1, 10).unwrap();
+            ticket_counts[ticket as usize] += 1;
+        }
+
+        // Chi-squared with 9 degrees of freedom, 27.88 is the 0.1% critical value.
+        let expected = draws as f64 / 10.0;
+        let chi_squared: f64 = ticket_counts
+            .iter()
+            .map(|count| (*count as f64 - expected).powi(2) / expected)
+            .sum();
+        assert!(chi_squared < 27.88, "chi squared {}", chi_squared);
+
+        // Every player wins within 5 standard deviations of tickets / total_tickets.
+        for (player, tickets) in players {
+            let wins: u64 = (0..10)
+                .filter(|ticket| owners[*ticket] == player)
+                .map(|ticket| ticket_counts[ticket])
+                .sum();
+            let p = tickets as f64 / 10.0;
+            let deviation = (draws as f64 * p * (1.0 - p)).sqrt();
+            assert!(
+                (wins as f64 - draws as f64 * p).abs() < 5.0 * deviation,
+                "{} won {} of {} draws",
+                player,
+                wins,
+                draws
+            );
         }
     }
 }
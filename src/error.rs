@@ -8,9 +8,15 @@ pub enum ContractError {
     #[error("{0}")]
     OverFlowError(cosmwasm_std::OverflowError),
 
+    #[error("{0}")]
+    ConversionOverflowError(cosmwasm_std::ConversionOverflowError),
+
     #[error("{0}")]
     PaymentError(cw_utils::PaymentError),
 
+    #[error("{0}")]
+    ParseReplyError(cw_utils::ParseReplyError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -23,6 +29,141 @@ pub enum ContractError {
     #[error("The ticket buying process right now is closed.")]
     TicketBuyingNotAvailable {},
 
+    #[error("Tickets can only be transferred while the lottery is open.")]
+    TicketTransferNotAvailable {},
+
+    #[error("Not enough tickets owned for this transfer.")]
+    TicketTransferInsufficientTickets {},
+
+    #[error("At least one ticket has to be transferred.")]
+    TicketTransferZeroAmount {},
+
+    #[error("cw20 tickets have to be transferred through the ticket token.")]
+    TicketTransferUseTicketToken {},
+
+    #[error("The seed commitment must be a 32 byte sha256 hash.")]
+    InvalidSeedCommitment {},
+
+    #[error("The revealed seed and salt don't match the round's seed commitment.")]
+    SeedCommitmentMismatch {},
+
+    #[error("This randomness source needs a commitment for each round.")]
+    MissingSeedCommitment {},
+
+    #[error("The drand public key or period is invalid.")]
+    InvalidDrandConfig {},
+
+    #[error("The submitted beacon isn't a valid signature for drand round {round}.")]
+    InvalidDrandBeacon { round: u64 },
+
+    #[error("This lottery is drawn from a drand beacon, not from a seed.")]
+    DrawRequiresDrandBeacon {},
+
+    #[error("This lottery isn't configured to be drawn from drand.")]
+    DrandNotConfigured {},
+
+    #[error("This lottery is drawn from randomness oracle callbacks, not from a seed.")]
+    DrawRequiresOracle {},
+
+    #[error("This lottery isn't configured to be drawn from a randomness oracle.")]
+    OracleNotConfigured {},
+
+    #[error("The oracle callback is for job {job_id}, not the current round's.")]
+    OracleJobMismatch { job_id: String },
+
+    #[error("This lottery is drawn from an operator signature, not from a seed.")]
+    DrawRequiresOperatorSignature {},
+
+    #[error("This lottery isn't configured to be drawn from an operator signature.")]
+    OperatorSignatureNotConfigured {},
+
+    #[error("The operator public key doesn't match the signature scheme.")]
+    InvalidOperatorPubkey {},
+
+    #[error("The signature doesn't match the operator key and the round.")]
+    InvalidOperatorSignature {},
+
+    #[error("The signature doesn't use the nonce the operator committed to for the round.")]
+    OperatorNonceMismatch {},
+
+    #[error("This lottery is drawn from randomness sent over IBC, not from a seed.")]
+    DrawRequiresIbcRandomness {},
+
+    #[error("This lottery isn't configured to be drawn from IBC randomness.")]
+    IbcRandomnessNotConfigured {},
+
+    #[error("Randomness requests need a timeout of at least a second.")]
+    InvalidIbcTimeout {},
+
+    #[error("No channel to the randomness provider is open.")]
+    NoRandomnessChannel {},
+
+    #[error("A randomness request for this round is already in flight or accepted.")]
+    RandomnessRequestInFlight {},
+
+    #[error("Only unordered channels are supported.")]
+    InvalidIbcOrder {},
+
+    #[error("Invalid IBC channel version, expected {version}.")]
+    InvalidIbcVersion { version: String },
+
+    #[error("Channels can only be opened on connection {connection_id}.")]
+    InvalidIbcConnection { connection_id: String },
+
+    #[error("A channel to the randomness provider is already open.")]
+    RandomnessChannelAlreadyOpen {},
+
+    #[error("Packets are only accepted from the randomness provider channel.")]
+    UnknownRandomnessChannel {},
+
+    #[error("The beacon is for round {round} but the lottery is drawing round {expected}.")]
+    RandomnessRoundMismatch { round: u64, expected: u64 },
+
+    #[error("Randomness must be exactly 32 bytes.")]
+    InvalidRandomness {},
+
+    #[error("The bond must be paid in the ticket denom.")]
+    InvalidBond {},
+
+    #[error("The exact bond of {bond} has to be posted with the round.")]
+    BondIncorrectAmount { bond: String },
+
+    #[error("The keeper bounty must be a share of the pool of at most 10% or a fixed amount above zero.")]
+    InvalidKeeperBounty {},
+
+    #[error("The round can still be drawn, its draw deadline hasn't passed.")]
+    DrawDeadlineNotReached {},
+
+    #[error("The ticket holders couldn't be snapshotted, the round can only be refunded.")]
+    TicketSnapshotFailed {},
+
+    #[error("The round's draw deadline has passed, players are being refunded.")]
+    DrawDeadlinePassed {},
+
+    #[error("Refunds are only available once a round misses its draw deadline.")]
+    RefundNotAvailable {},
+
+    #[error("No tickets to refund for this address.")]
+    NothingToRefund {},
+
+    #[error("The tickets of this address have already been refunded.")]
+    RefundAlreadyClaimed {},
+
+    #[error("This contract is already registered as a hook.")]
+    HookAlreadyRegistered {},
+
+    #[error("This contract is not registered as a hook.")]
+    HookNotRegistered {},
+
+    #[error("Closing sales has to pass the oracle fee of {fee} through.")]
+    OracleFeeNotPaid { fee: String },
+
+    #[error("At most {max} hooks can be registered.")]
+    TooManyHooks { max: usize },
+
+    #[error("A new round can only start once the current winner has claimed or its refund window has passed.")]
+    RoundNotFinished {},
+
     #[error("The current lottery is not executable because it is decided or still open.")]
     LotteryNotExecutable {},
 
@@ -38,6 +179,12 @@ pub enum ContractError {
     #[error("The current lottery winner has already claimed earnings")]
     LotteryNotClaimedByCorrectUser {},
 
+    #[error("The lottery ticket sales are still open.")]
+    TicketSalesStillOpen {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
 }
@@ -48,6 +195,12 @@ impl From<cw_utils::PaymentError> for ContractError {
     }
 }
 
+impl From<cw_utils::ParseReplyError> for ContractError {
+    fn from(err: cw_utils::ParseReplyError) -> Self {
+        ContractError::ParseReplyError(err)
+    }
+}
+
 impl From<cosmwasm_std::StdError> for ContractError {
     fn from(err: cosmwasm_std::StdError) -> Self {
         ContractError::Std(err)
@@ -59,3 +212,9 @@ impl From<cosmwasm_std::OverflowError> for ContractError {
         ContractError::OverFlowError(err)
     }
 }
+
+impl From<cosmwasm_std::ConversionOverflowError> for ContractError {
+    fn from(err: cosmwasm_std::ConversionOverflowError) -> Self {
+        ContractError::ConversionOverflowError(err)
+    }
+}
@@ -0,0 +1,170 @@
+use cosmwasm_std::{to_vec, Addr, Coin, Event, StdResult};
+use cw_utils::Expiration;
+
+use crate::msg::PayoutPreviewResponse;
+use crate::state::{Bond, BondStatus, Config, DrawRecord, Purchase, RefundPool};
+
+/*
+Events emitted for indexers. Every event carries the round it belongs to, amounts are coins
+formatted as "<amount><denom>" and ticket ranges are [ticket_start, ticket_end) indices.
+Together they're enough to rebuild every round's tickets, draw and payouts:
+
+- round_started: the end of ticket sales and the bond posted, on instantiation and StartRound
+- purchase: a BuyTicket, with its ticket range in native mode
+- ticket_transfer: a TransferTickets, with every ticket range it moved
+- ticket_snapshot: a cw20 ticket holder's range, when sales close
+- sales_closed: the round's total tickets and draw deadline
+- keeper_bounty: the bounty paid to a keeper closing sales or drawing
+- draw: the winning ticket and winner, whatever the randomness source
+- draw_expired: the refunds owed and what happened to the bond, once the draw deadline passed
+- claim: the winner's prize and every fee paid out
+- refund: a ClaimRefund once a round missed its draw deadline
+- config_change: the whole config, on instantiation and whenever it changes
+*/
+
+pub fn round_started_event(round: u64, sales_end: &Expiration, bond: Option<&Coin>) -> Event {
+    let event = Event::new("round_started")
+        .add_attribute("round", round.to_string())
+        .add_attribute("sales_end", sales_end.to_string());
+    match bond {
+        Some(bond) => event.add_attribute("bond", bond.to_string()),
+        None => event,
+    }
+}
+
+pub fn purchase_event(purchase: &Purchase, ticket_start: Option<u64>) -> Event {
+    let event = Event::new("purchase")
+        .add_attribute("round", purchase.round.to_string())
+        .add_attribute("purchase_id", purchase.id.to_string())
+        .add_attribute("buyer", &purchase.buyer)
+        .add_attribute("recipient", &purchase.recipient)
+        .add_attribute("tickets", purchase.tickets.to_string())
+        .add_attribute("paid", purchase.paid.to_string());
+    match ticket_start {
+        Some(start) => ticket_range_attributes(event, start, purchase.tickets),
+        None => event,
+    }
+}
+
+// Repeats a ticket_start and ticket_end pair per range moved, in the order they were moved.
+pub fn ticket_transfer_event(
+    round: u64,
+    sender: &Addr,
+    recipient: &Addr,
+    tickets: u64,
+    ranges: &[(u64, u64)],
+) -> Event {
+    let mut event = Event::new("ticket_transfer")
+        .add_attribute("round", round.to_string())
+        .add_attribute("sender", sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("tickets", tickets.to_string());
+    for (start, end) in ranges {
+        event = ticket_range_attributes(event, *start, end - start);
+    }
+    event
+}
+
+pub fn ticket_snapshot_event(round: u64, holder: &Addr, ticket_start: u64, tickets: u64) -> Event {
+    let event = Event::new("ticket_snapshot")
+        .add_attribute("round", round.to_string())
+        .add_attribute("holder", holder)
+        .add_attribute("tickets", tickets.to_string());
+    ticket_range_attributes(event, ticket_start, tickets)
+}
+
+fn ticket_range_attributes(event: Event, start: u64, tickets: u64) -> Event {
+    event
+        .add_attribute("ticket_start", start.to_string())
+        .add_attribute("ticket_end", (start + tickets).to_string())
+}
+
+pub fn sales_closed_event(
+    round: u64,
+    total_tickets: u64,
+    draw_deadline: &Expiration,
+    drand_round: Option<u64>,
+) -> Event {
+    let event = Event::new("sales_closed")
+        .add_attribute("round", round.to_string())
+        .add_attribute("total_tickets", total_tickets.to_string())
+        .add_attribute("draw_deadline", draw_deadline.to_string());
+    match drand_round {
+        Some(drand_round) => event.add_attribute("drand_round", drand_round.to_string()),
+        None => event,
+    }
+}
+
+pub fn keeper_bounty_event(round: u64, keeper: &Addr, bounty: &Coin) -> Event {
+    Event::new("keeper_bounty")
+        .add_attribute("round", round.to_string())
+        .add_attribute("keeper", keeper)
+        .add_attribute("bounty", bounty.to_string())
+}
+
+pub fn draw_event(round: u64, draw: &DrawRecord) -> Event {
+    Event::new("draw")
+        .add_attribute("round", round.to_string())
+        .add_attribute("winner", &draw.winner)
+        .add_attribute("winning_ticket", draw.winning_ticket.to_string())
+        .add_attribute("total_tickets", draw.total_tickets.to_string())
+        .add_attribute("entropy", draw.entropy.to_hex())
+}
+
+pub fn draw_expired_event(
+    round: u64,
+    total_tickets: u64,
+    refund_pool: &RefundPool,
+    bond: Option<&Bond>,
+    denom: &str,
+) -> Event {
+    let event = Event::new("draw_expired")
+        .add_attribute("round", round.to_string())
+        .add_attribute("total_tickets", total_tickets.to_string())
+        .add_attribute(
+            "refunds_owed",
+            Coin::new(refund_pool.outstanding.u128(), denom).to_string(),
+        )
+        .add_attribute("claim_deadline", refund_pool.claim_deadline.to_string());
+    match bond {
+        Some(Bond {
+            amount,
+            status: BondStatus::Slashed {},
+        }) => event.add_attribute("bond_slashed", amount.to_string()),
+        Some(Bond { amount, .. }) => event.add_attribute("bond_returned", amount.to_string()),
+        None => event,
+    }
+}
+
+pub fn claim_event(winner: &Addr, payout: &PayoutPreviewResponse) -> Event {
+    let mut event = Event::new("claim")
+        .add_attribute("round", payout.round.to_string())
+        .add_attribute("winner", winner)
+        .add_attribute("prize", payout.winner_amount.to_string())
+        .add_attribute("gross_pool", payout.gross_pool.to_string());
+    // Repeated in the order the fees are paid
+    for fee in &payout.fees {
+        event = event
+            .add_attribute("fee_recipient", &fee.recipient)
+            .add_attribute("fee", fee.amount.to_string());
+    }
+    match &payout.bond {
+        Some(bond) => event.add_attribute("bond_returned", bond.to_string()),
+        None => event,
+    }
+}
+
+pub fn refund_event(round: u64, player: &Addr, tickets: u64, refund: &Coin) -> Event {
+    Event::new("refund")
+        .add_attribute("round", round.to_string())
+        .add_attribute("player", player)
+        .add_attribute("tickets", tickets.to_string())
+        .add_attribute("amount", refund.to_string())
+}
+
+// The config is serialized as JSON, the same as the Config query returns it.
+pub fn config_change_event(round: u64, config: &Config) -> StdResult<Event> {
+    Ok(Event::new("config_change")
+        .add_attribute("round", round.to_string())
+        .add_attribute("config", String::from_utf8_lossy(&to_vec(config)?)))
+}
@@ -1,9 +1,267 @@
-use cosmwasm_std::{Addr, DepsMut, Order, StdResult};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, HexBinary, QuerierWrapper, StdResult, WasmMsg,
+};
+use serde::de::DeserializeOwned;
 
-use crate::state::PLAYERS;
+use crate::msg::{
+    BondResponse, ConfigResponse, ExecuteMsg, HooksResponse, LotteryStateResponse, OddsResponse,
+    OperatorMessageResponse, OracleCallback, ParticipantEntropyResponse, PayoutPreviewResponse,
+    PlayerHistoryResponse, PlayersResponse, PurchaseResponse, PurchasesResponse, QueryMsg,
+    RandomnessRequestResponse, SimulatePurchaseResponse, StatsResponse, StatusResponse,
+    TicketResponse, TopWinnersResponse, VerifyDrawResponse,
+};
+use crate::state::RandomnessSource;
 
-pub fn get_player_ranges<'a>(
-    deps: &'a DepsMut,
-) -> Box<dyn Iterator<Item = StdResult<(Addr, u64)>> + 'a> {
-    PLAYERS.range(deps.storage, None, None, Order::Descending)
+// A deployed lottery, for contracts that integrate with it. Build it with the `library` feature
+// to leave out the entry points. Execute helpers return the message to add to a response, query
+// helpers return the typed response.
+#[cw_serde]
+pub struct LotteryContract(pub Addr);
+
+impl LotteryContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg.into())?,
+            funds,
+        }
+        .into())
+    }
+
+    // `funds` has to be exactly the cost of the tickets.
+    pub fn buy_ticket(
+        &self,
+        num_tickets: u64,
+        recipient: Option<String>,
+        entropy: Option<HexBinary>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::BuyTicket {
+                num_tickets,
+                recipient,
+                entropy,
+            },
+            funds,
+        )
+    }
+
+    pub fn transfer_tickets(&self, recipient: String, amount: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::TransferTickets { recipient, amount }, vec![])
+    }
+
+    pub fn execute_lottery(&self, seed: u64, salt: HexBinary) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ExecuteLottery { seed, salt }, vec![])
+    }
+
+    pub fn claim_tokens(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimTokens {}, vec![])
+    }
+
+    // `funds` carries the oracle fee with `Oracle` randomness, leave it empty otherwise.
+    pub fn close_sales(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CloseSales {}, funds)
+    }
+
+    // `funds` carries the bond, if one is configured.
+    pub fn start_round(
+        &self,
+        seed_commitment: Option<HexBinary>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::StartRound { seed_commitment }, funds)
+    }
+
+    pub fn submit_drand_beacon(
+        &self,
+        signature: HexBinary,
+        previous_signature: HexBinary,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::SubmitDrandBeacon {
+                signature,
+                previous_signature,
+            },
+            vec![],
+        )
+    }
+
+    pub fn request_randomness(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RequestRandomness {}, vec![])
+    }
+
+    pub fn submit_operator_signature(&self, signature: HexBinary) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SubmitOperatorSignature { signature }, vec![])
+    }
+
+    pub fn nois_receive(&self, callback: OracleCallback) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::NoisReceive { callback }, vec![])
+    }
+
+    pub fn expire_draw(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ExpireDraw {}, vec![])
+    }
+
+    pub fn claim_refund(&self, round: Option<u64>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimRefund { round }, vec![])
+    }
+
+    pub fn set_randomness_source(&self, randomness: RandomnessSource) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetRandomnessSource { randomness }, vec![])
+    }
+
+    pub fn add_hook(&self, addr: String, gas_limit: Option<u64>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::AddHook { addr, gas_limit }, vec![])
+    }
+
+    pub fn remove_hook(&self, addr: String) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RemoveHook { addr }, vec![])
+    }
+
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper,
+        msg: QueryMsg,
+    ) -> StdResult<T> {
+        querier.query_wasm_smart(self.addr(), &msg)
+    }
+
+    pub fn ticket_count(&self, querier: &QuerierWrapper, addr: Addr) -> StdResult<TicketResponse> {
+        self.query(querier, QueryMsg::TicketCount { addr })
+    }
+
+    pub fn lottery_state(&self, querier: &QuerierWrapper) -> StdResult<LotteryStateResponse> {
+        self.query(querier, QueryMsg::LotteryState {})
+    }
+
+    pub fn status(&self, querier: &QuerierWrapper) -> StdResult<StatusResponse> {
+        self.query(querier, QueryMsg::Status {})
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
+        self.query(querier, QueryMsg::Config {})
+    }
+
+    pub fn hooks(&self, querier: &QuerierWrapper) -> StdResult<HooksResponse> {
+        self.query(querier, QueryMsg::Hooks {})
+    }
+
+    pub fn players(
+        &self,
+        querier: &QuerierWrapper,
+        round: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<PlayersResponse> {
+        self.query(
+            querier,
+            QueryMsg::Players {
+                round,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn randomness_request(
+        &self,
+        querier: &QuerierWrapper,
+    ) -> StdResult<RandomnessRequestResponse> {
+        self.query(querier, QueryMsg::RandomnessRequest {})
+    }
+
+    pub fn participant_entropy(
+        &self,
+        querier: &QuerierWrapper,
+    ) -> StdResult<ParticipantEntropyResponse> {
+        self.query(querier, QueryMsg::ParticipantEntropy {})
+    }
+
+    pub fn player_history(
+        &self,
+        querier: &QuerierWrapper,
+        addr: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PlayerHistoryResponse> {
+        self.query(
+            querier,
+            QueryMsg::PlayerHistory {
+                addr,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn purchases(
+        &self,
+        querier: &QuerierWrapper,
+        round: Option<u64>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PurchasesResponse> {
+        self.query(
+            querier,
+            QueryMsg::Purchases {
+                round,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn purchase(&self, querier: &QuerierWrapper, id: u64) -> StdResult<PurchaseResponse> {
+        self.query(querier, QueryMsg::Purchase { id })
+    }
+
+    pub fn stats(&self, querier: &QuerierWrapper) -> StdResult<StatsResponse> {
+        self.query(querier, QueryMsg::Stats {})
+    }
+
+    pub fn top_winners(
+        &self,
+        querier: &QuerierWrapper,
+        limit: Option<u32>,
+    ) -> StdResult<TopWinnersResponse> {
+        self.query(querier, QueryMsg::TopWinners { limit })
+    }
+
+    pub fn odds(&self, querier: &QuerierWrapper, addr: String) -> StdResult<OddsResponse> {
+        self.query(querier, QueryMsg::Odds { addr })
+    }
+
+    pub fn simulate_purchase(
+        &self,
+        querier: &QuerierWrapper,
+        addr: String,
+        num_tickets: u64,
+    ) -> StdResult<SimulatePurchaseResponse> {
+        self.query(querier, QueryMsg::SimulatePurchase { addr, num_tickets })
+    }
+
+    pub fn operator_message(&self, querier: &QuerierWrapper) -> StdResult<OperatorMessageResponse> {
+        self.query(querier, QueryMsg::OperatorMessage {})
+    }
+
+    pub fn payout_preview(&self, querier: &QuerierWrapper) -> StdResult<PayoutPreviewResponse> {
+        self.query(querier, QueryMsg::PayoutPreview {})
+    }
+
+    pub fn bond(&self, querier: &QuerierWrapper, round: Option<u64>) -> StdResult<BondResponse> {
+        self.query(querier, QueryMsg::Bond { round })
+    }
+
+    pub fn verify_draw(
+        &self,
+        querier: &QuerierWrapper,
+        round: u64,
+    ) -> StdResult<VerifyDrawResponse> {
+        self.query(querier, QueryMsg::VerifyDraw { round })
+    }
 }
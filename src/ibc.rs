@@ -0,0 +1,523 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, DepsMut, Env, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcMsg, IbcOrder, IbcPacket,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout,
+    Response, StdResult, Storage,
+};
+
+use crate::constants::IBC_APP_VERSION;
+use crate::contract::draw_winner;
+use crate::error::ContractError;
+use crate::msg::{RandomnessAck, RandomnessPacket};
+use crate::state::{
+    IbcRandomnessConfig, LotteryState, RandomnessRequestStatus, RandomnessSource, CONFIG,
+    LOTTERY_STATE, RANDOMNESS_CHANNEL, RANDOMNESS_REQUEST, ROUND,
+};
+
+/*
+Randomness can be provided by another chain (e.g. Secret Network) over IBC.
+
+- When sales close the lottery sends a Request packet for the round to the provider
+- The provider acknowledges the request, and later sends a Beacon packet back with the randomness
+- Receiving the Beacon packet draws the winner
+- If the request times out or is rejected, anyone can send it again with RequestRandomness
+*/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    validate_channel(deps.storage, msg.channel(), msg.counterparty_version())?;
+    Ok(None)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.channel();
+    validate_channel(deps.storage, channel, msg.counterparty_version())?;
+    RANDOMNESS_CHANNEL.save(deps.storage, &channel.endpoint.channel_id)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_connect")
+        .add_attribute("channel_id", &channel.endpoint.channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    // Once the provider channel is gone a new one can be opened in its place.
+    let channel_id = &msg.channel().endpoint.channel_id;
+    if RANDOMNESS_CHANNEL.may_load(deps.storage)?.as_ref() == Some(channel_id) {
+        RANDOMNESS_CHANNEL.remove(deps.storage);
+    }
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_close")
+        .add_attribute("channel_id", channel_id))
+}
+
+fn validate_channel(
+    storage: &dyn Storage,
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    let ibc_randomness = ibc_randomness_config(storage)?;
+
+    if channel.order != IbcOrder::Unordered {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    if channel.version != IBC_APP_VERSION
+        || counterparty_version.is_some_and(|version| version != IBC_APP_VERSION)
+    {
+        return Err(ContractError::InvalidIbcVersion {
+            version: IBC_APP_VERSION.to_string(),
+        });
+    }
+    if channel.connection_id != ibc_randomness.connection_id {
+        return Err(ContractError::InvalidIbcConnection {
+            connection_id: ibc_randomness.connection_id,
+        });
+    }
+    if RANDOMNESS_CHANNEL.may_load(storage)?.is_some() {
+        return Err(ContractError::RandomnessChannelAlreadyOpen {});
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    // Failures are sent back to the provider as an error acknowledgement instead of
+    // aborting the relay, nothing is written to storage before the draw succeeds.
+    match receive_beacon(deps, &env, &msg.packet) {
+        Ok(response) => Ok(response),
+        Err(err) => Ok(IbcReceiveResponse::new()
+            .set_ack(to_binary(&RandomnessAck::Error(err.to_string()))?)
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", err.to_string())),
+    }
+}
+
+fn receive_beacon(
+    deps: DepsMut,
+    env: &Env,
+    packet: &IbcPacket,
+) -> Result<IbcReceiveResponse, ContractError> {
+    if RANDOMNESS_CHANNEL.may_load(deps.storage)?.as_ref() != Some(&packet.dest.channel_id) {
+        return Err(ContractError::UnknownRandomnessChannel {});
+    }
+
+    let (round, randomness) = match from_binary(&packet.data)? {
+        RandomnessPacket::Beacon { round, randomness } => (round, randomness),
+        RandomnessPacket::Request { .. } => return Err(ContractError::InvalidRandomness {}),
+    };
+    let expected = ROUND.load(deps.storage)?;
+    if round != expected {
+        return Err(ContractError::RandomnessRoundMismatch { round, expected });
+    }
+    let randomness: [u8; 32] = randomness
+        .to_array()
+        .map_err(|_| ContractError::InvalidRandomness {})?;
+
+    match LOTTERY_STATE.load(deps.storage)? {
+        LotteryState::CHOOSING {} => {
+            let draw = draw_winner(deps, env, randomness)?;
+            Ok(IbcReceiveResponse::new()
+                .set_ack(to_binary(&RandomnessAck::Result(Binary::default()))?)
+                .add_submessages(draw.hook_msgs)
+                .add_event(draw.event)
+                .add_attribute("method", "ibc_packet_receive")
+                .add_attribute("round", round.to_string())
+                .add_attribute("winner", draw.winner))
+        }
+        LotteryState::OPEN { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => Err(ContractError::DrawDeadlinePassed {}),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let ack: RandomnessAck = from_binary(&msg.acknowledgement.data)?;
+    let status = match ack {
+        RandomnessAck::Result(_) => RandomnessRequestStatus::Accepted {},
+        RandomnessAck::Error(_) => RandomnessRequestStatus::Failed {},
+    };
+    update_request_status(deps.storage, &msg.original_packet, status)?;
+
+    Ok(IbcBasicResponse::new().add_attribute("method", "ibc_packet_ack"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    update_request_status(
+        deps.storage,
+        &msg.packet,
+        RandomnessRequestStatus::Failed {},
+    )?;
+
+    Ok(IbcBasicResponse::new().add_attribute("method", "ibc_packet_timeout"))
+}
+
+// Acks and timeouts for requests of a previous round are ignored.
+fn update_request_status(
+    storage: &mut dyn Storage,
+    packet: &IbcPacket,
+    status: RandomnessRequestStatus,
+) -> Result<(), ContractError> {
+    if let RandomnessPacket::Request { round } = from_binary(&packet.data)? {
+        if round == ROUND.load(storage)? && RANDOMNESS_REQUEST.may_load(storage)?.is_some() {
+            RANDOMNESS_REQUEST.save(storage, &status)?;
+        }
+    }
+    Ok(())
+}
+
+fn ibc_randomness_config(storage: &dyn Storage) -> Result<IbcRandomnessConfig, ContractError> {
+    match CONFIG.load(storage)?.randomness {
+        RandomnessSource::Ibc(ibc_randomness) => Ok(ibc_randomness),
+        _ => Err(ContractError::IbcRandomnessNotConfigured {}),
+    }
+}
+
+// The request packet for the current round, if a channel to the provider is open.
+pub(crate) fn randomness_request_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    ibc_randomness: &IbcRandomnessConfig,
+) -> StdResult<Option<IbcMsg>> {
+    let channel_id = match RANDOMNESS_CHANNEL.may_load(storage)? {
+        Some(channel_id) => channel_id,
+        None => return Ok(None),
+    };
+    RANDOMNESS_REQUEST.save(storage, &RandomnessRequestStatus::Pending {})?;
+    Ok(Some(IbcMsg::SendPacket {
+        channel_id,
+        data: to_binary(&RandomnessPacket::Request {
+            round: ROUND.load(storage)?,
+        })?,
+        timeout: IbcTimeout::with_timestamp(
+            env.block.time.plus_seconds(ibc_randomness.timeout_seconds),
+        ),
+    }))
+}
+
+pub(crate) fn execute_request_randomness(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let ibc_randomness = ibc_randomness_config(deps.storage)?;
+
+    match LOTTERY_STATE.load(deps.storage)? {
+        LotteryState::CHOOSING {} => {}
+        LotteryState::OPEN { .. } => return Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::CLOSED { .. } => return Err(ContractError::LotteryNotExecutable {}),
+        LotteryState::REFUNDING { .. } => return Err(ContractError::DrawDeadlinePassed {}),
+    }
+    match RANDOMNESS_REQUEST.may_load(deps.storage)? {
+        None | Some(RandomnessRequestStatus::Failed {}) => {}
+        Some(_) => return Err(ContractError::RandomnessRequestInFlight {}),
+    }
+
+    let request_msg = randomness_request_msg(deps.storage, &env, &ibc_randomness)?
+        .ok_or(ContractError::NoRandomnessChannel {})?;
+    Ok(Response::new()
+        .add_attribute("method", "request_randomness")
+        .add_message(request_msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_ibc_channel_connect_ack, mock_ibc_channel_open_init,
+        mock_ibc_channel_open_try, mock_ibc_packet_ack, mock_ibc_packet_recv,
+        mock_ibc_packet_timeout, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coins, from_binary, Addr, CosmosMsg, HexBinary, IbcAcknowledgement, IbcMsg, IbcOrder,
+        OwnedDeps,
+    };
+
+    use crate::constants::IBC_APP_VERSION;
+    use crate::contract::{execute, instantiate, query_lottery_state, query_randomness_request};
+    use crate::ibc::{
+        ibc_channel_connect, ibc_channel_open, ibc_packet_ack, ibc_packet_receive,
+        ibc_packet_timeout,
+    };
+    use crate::msg::{ExecuteMsg, InstantiateMsg, RandomnessAck, RandomnessPacket};
+    use crate::state::{
+        IbcRandomnessConfig, LotteryState, RandomnessRequestStatus, RandomnessSource,
+    };
+    use crate::test_util::tests::{
+        testing_instantiate_msg, TESTING_1_WEEK_IN_SECONDS, TESTING_NATIVE_DENOM,
+        TESTING_TICKET_COST, TEST_USER_1,
+    };
+    use crate::ContractError;
+
+    const CHANNEL: &str = "channel-0";
+    // The connection used by the cosmwasm-std IBC mocks
+    const CONNECTION: &str = "connection-2";
+
+    fn setup_ibc_lottery() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ibc_instantiate_msg(600),
+        )
+        .unwrap();
+        deps
+    }
+
+    fn ibc_instantiate_msg(timeout_seconds: u64) -> InstantiateMsg {
+        InstantiateMsg {
+            randomness: Some(RandomnessSource::Ibc(IbcRandomnessConfig {
+                connection_id: CONNECTION.to_string(),
+                timeout_seconds,
+            })),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        }
+    }
+
+    #[test]
+    fn randomness_requests_need_a_timeout() {
+        let mut deps = mock_dependencies();
+        assert_eq!(
+            instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                ibc_instantiate_msg(0),
+            )
+            .unwrap_err(),
+            ContractError::InvalidIbcTimeout {}
+        );
+    }
+
+    #[test]
+    fn channel_handshake_is_restricted_to_the_provider() {
+        let mut deps = setup_ibc_lottery();
+
+        assert_eq!(
+            ibc_channel_open(
+                deps.as_mut(),
+                mock_env(),
+                mock_ibc_channel_open_init(CHANNEL, IbcOrder::Ordered, IBC_APP_VERSION),
+            )
+            .unwrap_err(),
+            ContractError::InvalidIbcOrder {}
+        );
+        assert_eq!(
+            ibc_channel_open(
+                deps.as_mut(),
+                mock_env(),
+                mock_ibc_channel_open_try(CHANNEL, IbcOrder::Unordered, "ics20-1"),
+            )
+            .unwrap_err(),
+            ContractError::InvalidIbcVersion {
+                version: IBC_APP_VERSION.to_string()
+            }
+        );
+
+        ibc_channel_open(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_channel_open_init(CHANNEL, IbcOrder::Unordered, IBC_APP_VERSION),
+        )
+        .unwrap();
+        ibc_channel_connect(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_channel_connect_ack(CHANNEL, IbcOrder::Unordered, IBC_APP_VERSION),
+        )
+        .unwrap();
+
+        // Only a single provider channel is kept.
+        assert_eq!(
+            ibc_channel_open(
+                deps.as_mut(),
+                mock_env(),
+                mock_ibc_channel_open_init("channel-1", IbcOrder::Unordered, IBC_APP_VERSION),
+            )
+            .unwrap_err(),
+            ContractError::RandomnessChannelAlreadyOpen {}
+        );
+        assert_eq!(
+            query_randomness_request(deps.as_ref()).unwrap().channel,
+            Some(CHANNEL.to_string())
+        );
+    }
+
+    #[test]
+    fn closing_sales_requests_randomness_and_the_beacon_draws() {
+        let mut deps = setup_ibc_lottery();
+        ibc_channel_connect(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_channel_connect_ack(CHANNEL, IbcOrder::Unordered, IBC_APP_VERSION),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                TEST_USER_1,
+                &coins(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            ),
+            ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+        )
+        .unwrap();
+
+        let mut expired_env = mock_env();
+        expired_env.block.time = expired_env
+            .block
+            .time
+            .plus_seconds(TESTING_1_WEEK_IN_SECONDS + 1);
+        let close_response = execute(
+            deps.as_mut(),
+            expired_env.clone(),
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::CloseSales {},
+        )
+        .unwrap();
+
+        let request = RandomnessPacket::Request { round: 1 };
+        match &close_response.messages[0].msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket {
+                channel_id, data, ..
+            }) => {
+                assert_eq!(channel_id, CHANNEL);
+                assert_eq!(from_binary::<RandomnessPacket>(data).unwrap(), request);
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+
+        // Requests can't be duplicated while one is in flight, but can be retried after a timeout.
+        assert_eq!(
+            execute(
+                deps.as_mut(),
+                expired_env.clone(),
+                mock_info(TEST_USER_1, &[]),
+                ExecuteMsg::RequestRandomness {},
+            )
+            .unwrap_err(),
+            ContractError::RandomnessRequestInFlight {}
+        );
+        ibc_packet_timeout(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_packet_timeout(CHANNEL, &request).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            query_randomness_request(deps.as_ref()).unwrap().status,
+            Some(RandomnessRequestStatus::Failed {})
+        );
+        let retry_response = execute(
+            deps.as_mut(),
+            expired_env,
+            mock_info(TEST_USER_1, &[]),
+            ExecuteMsg::RequestRandomness {},
+        )
+        .unwrap();
+        assert_eq!(retry_response.messages.len(), 1);
+
+        ibc_packet_ack(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_packet_ack(
+                CHANNEL,
+                &request,
+                IbcAcknowledgement::encode_json(&RandomnessAck::Result(Default::default()))
+                    .unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            query_randomness_request(deps.as_ref()).unwrap().status,
+            Some(RandomnessRequestStatus::Accepted {})
+        );
+
+        // A beacon for another round is rejected with an error acknowledgement.
+        let stale_beacon = ibc_packet_receive(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_packet_recv(
+                CHANNEL,
+                &RandomnessPacket::Beacon {
+                    round: 0,
+                    randomness: HexBinary::from([7u8; 32]),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<RandomnessAck>(&stale_beacon.acknowledgement).unwrap(),
+            RandomnessAck::Error(
+                ContractError::RandomnessRoundMismatch {
+                    round: 0,
+                    expected: 1
+                }
+                .to_string()
+            )
+        );
+
+        let beacon = ibc_packet_receive(
+            deps.as_mut(),
+            mock_env(),
+            mock_ibc_packet_recv(
+                CHANNEL,
+                &RandomnessPacket::Beacon {
+                    round: 1,
+                    randomness: HexBinary::from([7u8; 32]),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<RandomnessAck>(&beacon.acknowledgement).unwrap(),
+            RandomnessAck::Result(Default::default())
+        );
+        assert_eq!(
+            query_lottery_state(deps.as_ref(), mock_env())
+                .unwrap()
+                .lotto_state,
+            LotteryState::CLOSED {
+                winner: Addr::unchecked(TEST_USER_1),
+                claimed: false
+            }
+        );
+    }
+}
@@ -1,16 +1,41 @@
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::{coin, Addr, BlockInfo, Coin, Empty, Uint128};
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        coin, to_binary, Addr, Attribute, Binary, BlockInfo, Coin, Decimal, Empty, HexBinary,
+        Response, StdError, StdResult, Timestamp, Uint128, WasmMsg,
+    };
 
+    use cw20::Cw20ExecuteMsg;
     use cw_multi_test::{App, Contract, ContractWrapper, Executor};
-    use cw_utils::Duration;
+    use cw_storage_plus::Item;
+    use cw_utils::{Duration, Expiration};
+    use ed25519_zebra::{SigningKey, VerificationKey};
+    use sha2::{Digest, Sha256};
 
-    use crate::msg::{ExecuteMsg, InstantiateMsg, LotteryStateResponse, QueryMsg, TicketResponse};
-    use crate::state::LotteryState;
+    use crate::constants::{
+        DEFAULT_DRAND_SAFETY_MARGIN, DEFAULT_DRAW_DEADLINE_SECONDS, DEFAULT_HOOK_GAS_LIMIT,
+        MAX_HOOKS, MAX_TICKET_SNAPSHOT_PASSES,
+    };
+    use crate::helpers::LotteryContract;
+    use crate::msg::{
+        BondResponse, ConfigResponse, ExecuteMsg, FeeCut, HooksResponse, InstantiateMsg,
+        LotteryHookMsg, LotteryStateResponse, OperatorMessageResponse, OracleCallback,
+        PayoutPreviewResponse, QueryMsg, TicketResponse, VerifyDrawResponse,
+    };
+    use crate::state::{
+        Bond, BondStatus, Hook, KeeperBounty, LotteryState, OperatorSignatureConfig, OracleConfig,
+        RandomnessSource, SignatureScheme, TicketMode,
+    };
     use crate::test_util::tests::{
-        TESTING_DURATION, TESTING_NATIVE_DENOM, TESTING_TICKET_COST, TEST_ADMIN, TEST_GOD,
-        TEST_USER_1, TEST_USER_2, TEST_USER_3,
+        ed25519_nonce_commitment, ed25519_sign_with_nonce, testing_drand_config,
+        testing_instantiate_msg, testing_salt, testing_seed_commitment, TESTING_1_WEEK_IN_SECONDS,
+        TESTING_DRAND_GENESIS_TIME, TESTING_DRAND_PERIOD, TESTING_DRAND_PREVIOUS_SIGNATURE,
+        TESTING_DRAND_ROUND, TESTING_DRAND_SIGNATURE, TESTING_DURATION, TESTING_NATIVE_DENOM,
+        TESTING_SEED, TESTING_TICKET_COST, TEST_ADMIN, TEST_GOD, TEST_USER_1, TEST_USER_2,
+        TEST_USER_3,
     };
+    use crate::util::{self, ledger_hash, seed_commitment};
     use crate::ContractError;
 
     fn expire(voting_period: Duration) -> impl Fn(&mut BlockInfo) {
@@ -34,6 +59,16 @@ mod tests {
             crate::contract::execute,
             crate::contract::instantiate,
             crate::contract::query,
+        )
+        .with_reply(crate::contract::reply);
+        Box::new(contract)
+    }
+
+    pub fn contract_ticket_token() -> Box<dyn Contract<Empty>> {
+        let contract = ContractWrapper::new(
+            cw20_base::contract::execute,
+            cw20_base::contract::instantiate,
+            cw20_base::contract::query,
         );
         Box::new(contract)
     }
@@ -42,12 +77,7 @@ mod tests {
     fn instantiate_buy_1_ticket_and_execute() {
         let (mut app, lotto_code_id) = setup_app();
 
-        let instantiate_message = InstantiateMsg {
-            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
-            lottery_duration: TESTING_DURATION,
-            admin: TEST_ADMIN.to_string(),
-            house_fee: 500, // 5%
-        };
+        let instantiate_message = testing_instantiate_msg();
 
         let lotto_contract_addr = app
             .instantiate_contract(
@@ -64,7 +94,11 @@ mod tests {
             .execute_contract(
                 Addr::unchecked(TEST_USER_1),
                 lotto_contract_addr.clone(),
-                &ExecuteMsg::BuyTicket { num_tickets: 1 },
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
                 &[Coin {
                     denom: TESTING_NATIVE_DENOM.to_string(),
                     amount: Uint128::new(1_000u128),
@@ -84,17 +118,13 @@ mod tests {
 
         app.update_block(expire(TESTING_DURATION));
 
-        // Note that this would be empty, shit would return OK.
-        // This is because this would update the contract to next state. However,
-        // the next call would fail.
-        let _buy_ticket_response_2 = app
-            .execute_contract(
-                Addr::unchecked(TEST_USER_2),
-                lotto_contract_addr.clone(),
-                &ExecuteMsg::BuyTicket { num_tickets: 1 },
-                &[],
-            )
-            .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
 
         let ticket_response_for_user_1: TicketResponse = app
             .wrap()
@@ -128,7 +158,11 @@ mod tests {
             .execute_contract(
                 Addr::unchecked(TEST_USER_3),
                 lotto_contract_addr.clone(),
-                &ExecuteMsg::BuyTicket { num_tickets: 1 },
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
                 &[],
             )
             .unwrap_err();
@@ -142,7 +176,10 @@ mod tests {
             .execute_contract(
                 Addr::unchecked(TEST_ADMIN),
                 lotto_contract_addr.clone(),
-                &ExecuteMsg::ExecuteLottery { seed: 12 },
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
                 &[],
             )
             .unwrap();
@@ -159,7 +196,10 @@ mod tests {
                     winner: (Addr::unchecked(TEST_USER_1)),
                     claimed: false
                 },
-                total_tickets: 1
+                total_tickets: 1,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
             }
         );
 
@@ -184,7 +224,10 @@ mod tests {
                     winner: (Addr::unchecked(TEST_USER_1)),
                     claimed: true
                 },
-                total_tickets: 1
+                total_tickets: 1,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
             }
         );
 
@@ -228,12 +271,7 @@ mod tests {
     fn instantiate_buy_with_two_players_ticket_and_execute() {
         let (mut app, lotto_code_id) = setup_app();
 
-        let instantiate_message = InstantiateMsg {
-            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
-            lottery_duration: TESTING_DURATION,
-            admin: TEST_ADMIN.to_string(),
-            house_fee: 500, // 5%
-        };
+        let instantiate_message = testing_instantiate_msg();
 
         let lotto_contract_addr = app
             .instantiate_contract(
@@ -249,7 +287,11 @@ mod tests {
         app.execute_contract(
             Addr::unchecked(TEST_USER_1),
             lotto_contract_addr.clone(),
-            &ExecuteMsg::BuyTicket { num_tickets: 1 },
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
             &[Coin {
                 denom: TESTING_NATIVE_DENOM.to_string(),
                 amount: Uint128::new(1_000u128),
@@ -260,7 +302,11 @@ mod tests {
         app.execute_contract(
             Addr::unchecked(TEST_USER_1),
             lotto_contract_addr.clone(),
-            &ExecuteMsg::BuyTicket { num_tickets: 2 },
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
             &[Coin {
                 denom: TESTING_NATIVE_DENOM.to_string(),
                 amount: Uint128::new(2_000u128),
@@ -271,7 +317,11 @@ mod tests {
         app.execute_contract(
             Addr::unchecked(TEST_USER_2),
             lotto_contract_addr.clone(),
-            &ExecuteMsg::BuyTicket { num_tickets: 3 },
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 3,
+                recipient: None,
+                entropy: None,
+            },
             &[Coin {
                 denom: TESTING_NATIVE_DENOM.to_string(),
                 amount: Uint128::new(3_000u128),
@@ -301,13 +351,33 @@ mod tests {
 
         app.update_block(expire(TESTING_DURATION));
 
-        // Note that this would be empty, shit would return OK.
-        // This is because this would update the contract to next state. However,
-        // the next call would fail.
+        // Sales are over once the lottery expires, a late purchase fails and keeps its funds.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_2),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
+                &[coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::TicketBuyingNotAvailable {},
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_2), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(2_000u128, TESTING_NATIVE_DENOM)
+        );
         app.execute_contract(
             Addr::unchecked(TEST_USER_2),
             lotto_contract_addr.clone(),
-            &ExecuteMsg::BuyTicket { num_tickets: 1 },
+            &ExecuteMsg::CloseSales {},
             &[],
         )
         .unwrap();
@@ -352,7 +422,11 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked(TEST_USER_3),
                 lotto_contract_addr.clone(),
-                &ExecuteMsg::BuyTicket { num_tickets: 1 },
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
                 &[],
             )
             .unwrap_err()
@@ -365,7 +439,10 @@ mod tests {
         app.execute_contract(
             Addr::unchecked(TEST_ADMIN),
             lotto_contract_addr.clone(),
-            &ExecuteMsg::ExecuteLottery { seed: 12 },
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
             &[],
         )
         .unwrap();
@@ -383,7 +460,10 @@ mod tests {
                     winner: (Addr::unchecked(TEST_USER_2)),
                     claimed: false
                 },
-                total_tickets: 6
+                total_tickets: 6,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
             }
         );
 
@@ -413,7 +493,10 @@ mod tests {
                     winner: (Addr::unchecked(TEST_USER_2)),
                     claimed: false
                 },
-                total_tickets: 6
+                total_tickets: 6,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
             }
         );
 
@@ -439,7 +522,10 @@ mod tests {
                     winner: (Addr::unchecked(TEST_USER_2)),
                     claimed: true
                 },
-                total_tickets: 6
+                total_tickets: 6,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
             }
         );
 
@@ -488,6 +574,2377 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cw20_tickets_are_tradable_and_snapshotted_when_sales_close() {
+        let (mut app, lotto_code_id) = setup_app();
+        let ticket_token_code_id = app.store_code(contract_ticket_token());
+
+        let instantiate_message = InstantiateMsg {
+            ticket_mode: Some(TicketMode::Cw20 {
+                code_id: ticket_token_code_id,
+                name: "Sphinx Ticket".to_string(),
+                symbol: "TICKET".to_string(),
+            }),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        let ticket_token = app
+            .wrap()
+            .query_wasm_smart::<ConfigResponse>(lotto_contract_addr.clone(), &QueryMsg::Config {})
+            .unwrap()
+            .ticket_token
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 3,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(3_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+
+        // User 1 hands all of their tickets to user 3 through the ticket token itself.
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            ticket_token,
+            &Cw20ExecuteMsg::Transfer {
+                recipient: TEST_USER_3.to_string(),
+                amount: Uint128::new(3u128),
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<TicketResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::TicketCount {
+                        addr: Addr::unchecked(TEST_USER_3),
+                    },
+                )
+                .unwrap(),
+            TicketResponse { tickets: Some(3) }
+        );
+
+        // Sales can't be closed before the lottery expires.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_2),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::CloseSales {},
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::TicketSalesStillOpen {}
+        );
+
+        app.update_block(expire(TESTING_DURATION));
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        // The snapshot now drives ticket counts, user 1 no longer holds any.
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<TicketResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::TicketCount {
+                        addr: Addr::unchecked(TEST_USER_1),
+                    },
+                )
+                .unwrap(),
+            TicketResponse { tickets: None }
+        );
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<TicketResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::TicketCount {
+                        addr: Addr::unchecked(TEST_USER_3),
+                    },
+                )
+                .unwrap(),
+            TicketResponse { tickets: Some(3) }
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<LotteryStateResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::LotteryState {}
+                )
+                .unwrap(),
+            LotteryStateResponse {
+                lotto_state: LotteryState::CLOSED {
+                    winner: (Addr::unchecked(TEST_USER_3)),
+                    claimed: false
+                },
+                total_tickets: 4,
+                round: 1,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
+            }
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr,
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+
+        // User 3 never paid for a ticket but wins the pool minus fees.
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_3), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(3_800u128, TESTING_NATIVE_DENOM)
+        );
+    }
+
+    #[test]
+    fn start_a_new_round_after_the_winner_claims() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = testing_instantiate_msg();
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+
+        // Only the admin can open the next round, with a fresh commitment.
+        let next_salt = HexBinary::from(b"round two");
+        let next_start_round = ExecuteMsg::StartRound {
+            seed_commitment: Some(seed_commitment(7, &next_salt)),
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &next_start_round,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::Unauthorized {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &next_start_round,
+            &[],
+        )
+        .unwrap();
+
+        // Tickets from the previous round don't carry over.
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<TicketResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::TicketCount {
+                        addr: Addr::unchecked(TEST_USER_1),
+                    },
+                )
+                .unwrap(),
+            TicketResponse { tickets: None }
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        // The first round's seed no longer opens the commitment.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::SeedCommitmentMismatch {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: 7,
+                salt: next_salt,
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<LotteryStateResponse>(
+                    lotto_contract_addr,
+                    &QueryMsg::LotteryState {}
+                )
+                .unwrap(),
+            LotteryStateResponse {
+                lotto_state: LotteryState::CLOSED {
+                    winner: (Addr::unchecked(TEST_USER_2)),
+                    claimed: false
+                },
+                total_tickets: 2,
+                round: 2,
+                drand_round: None,
+                draw_deadline: draw_deadline_from_now(&app),
+            }
+        );
+    }
+
+    #[test]
+    fn anyone_can_draw_with_the_drand_beacon_fixed_at_close() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        // Start the week so that sales close during the drand round that's the safety margin
+        // ahead of the one right before the test vector.
+        let close_time = TESTING_DRAND_GENESIS_TIME
+            + (TESTING_DRAND_ROUND - 2 - DEFAULT_DRAND_SAFETY_MARGIN) * TESTING_DRAND_PERIOD
+            + 10;
+        app.update_block(|block| {
+            block.time = Timestamp::from_seconds(close_time - TESTING_1_WEEK_IN_SECONDS - 1)
+        });
+
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::Drand(testing_drand_config())),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        // The drand round was fixed when sales closed and hasn't been published yet.
+        let lottery_state: LotteryStateResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::LotteryState {})
+            .unwrap();
+        assert_eq!(lottery_state.drand_round, Some(TESTING_DRAND_ROUND));
+
+        // The admin can no longer pick a seed.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::DrawRequiresDrandBeacon {}
+        );
+
+        // The previous round's beacon isn't accepted for the fixed round.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_3),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::SubmitDrandBeacon {
+                    signature: HexBinary::from_hex(TESTING_DRAND_PREVIOUS_SIGNATURE).unwrap(),
+                    previous_signature: HexBinary::from_hex(TESTING_DRAND_SIGNATURE).unwrap(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::InvalidDrandBeacon {
+                round: TESTING_DRAND_ROUND
+            }
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::SubmitDrandBeacon {
+                signature: HexBinary::from_hex(TESTING_DRAND_SIGNATURE).unwrap(),
+                previous_signature: HexBinary::from_hex(TESTING_DRAND_PREVIOUS_SIGNATURE).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        let lottery_state: LotteryStateResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::LotteryState {})
+            .unwrap();
+        assert_eq!(
+            lottery_state,
+            LotteryStateResponse {
+                lotto_state: LotteryState::CLOSED {
+                    winner: (Addr::unchecked(TEST_USER_1)),
+                    claimed: false
+                },
+                total_tickets: 4,
+                round: 1,
+                drand_round: Some(TESTING_DRAND_ROUND),
+                draw_deadline: draw_deadline_from_now(&app),
+            }
+        );
+    }
+
+    #[test]
+    fn switch_the_randomness_source_between_rounds() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = testing_instantiate_msg();
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        // The source of a round can't change once its tickets are on sale.
+        let set_admin_seed = ExecuteMsg::SetRandomnessSource {
+            randomness: RandomnessSource::AdminSeed {},
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &set_admin_seed,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RoundNotFinished {}
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &set_admin_seed,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::Unauthorized {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &set_admin_seed,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<ConfigResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::Config {}
+                )
+                .unwrap()
+                .config
+                .randomness,
+            RandomnessSource::AdminSeed {}
+        );
+
+        // The next round needs no commitment, the admin's seed is used as is.
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::StartRound {
+                seed_commitment: None,
+            },
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: 42,
+                salt: HexBinary::default(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            app.wrap()
+                .query_wasm_smart::<LotteryStateResponse>(
+                    lotto_contract_addr,
+                    &QueryMsg::LotteryState {}
+                )
+                .unwrap()
+                .lotto_state,
+            LotteryState::CLOSED {
+                winner: Addr::unchecked(TEST_USER_2),
+                claimed: false
+            }
+        );
+    }
+
+    #[test]
+    fn operator_signature_over_the_closed_ledger_draws_the_round() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        const OPERATOR_NONCE: [u8; 32] = [9u8; 32];
+        let operator_key = SigningKey::from([7u8; 32]);
+        let operator_pubkey = <[u8; 32]>::from(VerificationKey::from(&operator_key));
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::OperatorSignature(
+                OperatorSignatureConfig {
+                    scheme: SignatureScheme::Ed25519 {},
+                    pubkey: HexBinary::from(operator_pubkey),
+                },
+            )),
+            seed_commitment: Some(ed25519_nonce_commitment(OPERATOR_NONCE)),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::TransferTickets {
+                recipient: TEST_USER_2.to_string(),
+                amount: 1,
+            },
+            &[],
+        )
+        .unwrap();
+
+        // Nothing can be signed before the ledger is frozen.
+        assert!(app
+            .wrap()
+            .query_wasm_smart::<OperatorMessageResponse>(
+                lotto_contract_addr.clone(),
+                &QueryMsg::OperatorMessage {},
+            )
+            .is_err());
+
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        let operator_message: OperatorMessageResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::OperatorMessage {})
+            .unwrap();
+        let mut ledger = ledger_hash(None, None, &Addr::unchecked(TEST_USER_1), 2);
+        ledger = ledger_hash(
+            Some(&ledger),
+            Some(&Addr::unchecked(TEST_USER_1)),
+            &Addr::unchecked(TEST_USER_2),
+            1,
+        );
+        assert_eq!(operator_message.round, 1);
+        assert_eq!(operator_message.ledger_hash, ledger);
+        assert_eq!(
+            operator_message.message,
+            HexBinary::from(util::operator_message(&lotto_contract_addr, 1, &ledger))
+        );
+
+        // The admin can no longer pick a seed, and a signature over anything else is rejected.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::DrawRequiresOperatorSignature {}
+        );
+        let other_round = util::operator_message(&lotto_contract_addr, 2, &ledger);
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_3),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::SubmitOperatorSignature {
+                    signature: HexBinary::from(ed25519_sign_with_nonce(
+                        [7u8; 32],
+                        OPERATOR_NONCE,
+                        &other_round
+                    )),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::InvalidOperatorSignature {}
+        );
+
+        // A valid signature with any other nonce, here the RFC 8032 one, could give the
+        // operator another winner.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_3),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::SubmitOperatorSignature {
+                    signature: HexBinary::from(<[u8; 64]>::from(
+                        operator_key.sign(operator_message.message.as_slice())
+                    )),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::OperatorNonceMismatch {}
+        );
+
+        // Anyone can relay the operator's signature.
+        let signature = ed25519_sign_with_nonce(
+            [7u8; 32],
+            OPERATOR_NONCE,
+            operator_message.message.as_slice(),
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::SubmitOperatorSignature {
+                signature: HexBinary::from(signature),
+            },
+            &[],
+        )
+        .unwrap();
+
+        let verification: VerifyDrawResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::VerifyDraw { round: 1 })
+            .unwrap();
+        assert!(verification.verified);
+        assert_eq!(
+            verification.draw.source_entropy,
+            HexBinary::from(Sha256::digest(signature).as_slice())
+        );
+    }
+
+    #[cw_serde]
+    enum MockOracleExecuteMsg {
+        GetNextRandomness { job_id: String },
+        // Calls the requester back with the randomness of its last job
+        Fulfill { randomness: HexBinary },
+    }
+
+    const MOCK_ORACLE_JOB: Item<(Addr, String)> = Item::new("job");
+
+    // A randomness oracle with the same interface as a Nois proxy, fulfilling jobs on demand.
+    pub fn contract_mock_oracle() -> Box<dyn Contract<Empty>> {
+        let contract = ContractWrapper::new(
+            |deps, env, info, msg: MockOracleExecuteMsg| -> StdResult<Response> {
+                match msg {
+                    MockOracleExecuteMsg::GetNextRandomness { job_id } => {
+                        MOCK_ORACLE_JOB.save(deps.storage, &(info.sender, job_id))?;
+                        Ok(Response::new())
+                    }
+                    MockOracleExecuteMsg::Fulfill { randomness } => {
+                        let (requester, job_id) = MOCK_ORACLE_JOB.load(deps.storage)?;
+                        Ok(Response::new().add_message(WasmMsg::Execute {
+                            contract_addr: requester.to_string(),
+                            msg: to_binary(&ExecuteMsg::NoisReceive {
+                                callback: OracleCallback {
+                                    job_id,
+                                    published: env.block.time,
+                                    randomness,
+                                },
+                            })?,
+                            funds: vec![],
+                        }))
+                    }
+                }
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::new()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Err(StdError::generic_err("no queries")) },
+        );
+        Box::new(contract)
+    }
+
+    #[test]
+    fn oracle_callback_draws_the_round() {
+        let (mut app, lotto_code_id) = setup_app();
+        let oracle_code_id = app.store_code(contract_mock_oracle());
+        let oracle_addr = app
+            .instantiate_contract(
+                oracle_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &Empty {},
+                &[],
+                "oracle",
+                None,
+            )
+            .unwrap();
+
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::Oracle(OracleConfig {
+                contract: oracle_addr.clone(),
+                fee: Some(coin(100, TESTING_NATIVE_DENOM)),
+            })),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        for user in [TEST_USER_1, TEST_USER_2] {
+            app.execute_contract(
+                Addr::unchecked(user),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
+                &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+            )
+            .unwrap();
+        }
+
+        // Closing sales requests randomness from the oracle, the caller passes its fee through.
+        app.update_block(expire(TESTING_DURATION));
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::CloseSales {},
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::OracleFeeNotPaid {
+                fee: "100ulotto".to_string(),
+            }
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[coin(100, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(&oracle_addr, TESTING_NATIVE_DENOM)
+                .unwrap()
+                .amount,
+            Uint128::new(100)
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&lotto_contract_addr, TESTING_NATIVE_DENOM)
+                .unwrap()
+                .amount,
+            Uint128::new(2_000)
+        );
+
+        // Only the oracle can call back, and the admin can't draw in its place.
+        let callback = ExecuteMsg::NoisReceive {
+            callback: OracleCallback {
+                job_id: "lottery-round-1".to_string(),
+                published: app.block_info().time,
+                randomness: HexBinary::from([7u8; 32]),
+            },
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &callback,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::Unauthorized {}
+        );
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::DrawRequiresOracle {}
+        );
+
+        // Randomness that isn't 32 bytes long is refused.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                oracle_addr.clone(),
+                &MockOracleExecuteMsg::Fulfill {
+                    randomness: HexBinary::from([7u8; 16]),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::InvalidRandomness {}
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            oracle_addr,
+            &MockOracleExecuteMsg::Fulfill {
+                randomness: HexBinary::from([7u8; 32]),
+            },
+            &[],
+        )
+        .unwrap();
+
+        let lottery_state: LotteryStateResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::LotteryState {})
+            .unwrap();
+        assert!(matches!(
+            lottery_state.lotto_state,
+            LotteryState::CLOSED { claimed: false, .. }
+        ));
+        let verification: VerifyDrawResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::VerifyDraw { round: 1 })
+            .unwrap();
+        assert!(verification.verified);
+        assert_eq!(verification.draw.source_entropy, HexBinary::from([7u8; 32]));
+    }
+
+    #[cw_serde]
+    enum MockHookExecuteMsg {
+        LotteryHook(LotteryHookMsg),
+    }
+
+    const MOCK_HOOK_NOTIFICATIONS: Item<Vec<LotteryHookMsg>> = Item::new("notifications");
+    const MOCK_HOOK_FAILS: Item<bool> = Item::new("fails");
+
+    // Records every notification, or fails on all of them when instantiated with `true`.
+    pub fn contract_mock_hook() -> Box<dyn Contract<Empty>> {
+        let contract = ContractWrapper::new(
+            |deps, _, _, msg: MockHookExecuteMsg| -> StdResult<Response> {
+                if MOCK_HOOK_FAILS.load(deps.storage)? {
+                    return Err(StdError::generic_err("hook failed"));
+                }
+                let MockHookExecuteMsg::LotteryHook(notification) = msg;
+                let mut notifications = MOCK_HOOK_NOTIFICATIONS
+                    .may_load(deps.storage)?
+                    .unwrap_or_default();
+                notifications.push(notification);
+                MOCK_HOOK_NOTIFICATIONS.save(deps.storage, &notifications)?;
+                Ok(Response::new())
+            },
+            |deps, _, _, fails: bool| -> StdResult<Response> {
+                MOCK_HOOK_FAILS.save(deps.storage, &fails)?;
+                Ok(Response::new())
+            },
+            |deps, _, _: Empty| -> StdResult<Binary> {
+                to_binary(
+                    &MOCK_HOOK_NOTIFICATIONS
+                        .may_load(deps.storage)?
+                        .unwrap_or_default(),
+                )
+            },
+        );
+        Box::new(contract)
+    }
+
+    #[test]
+    fn hooks_are_notified_of_every_step_of_a_round() {
+        let (mut app, lotto_code_id) = setup_app();
+        let hook_code_id = app.store_code(contract_mock_hook());
+        let [hook_addr, failing_hook_addr] = [false, true].map(|fails| {
+            app.instantiate_contract(
+                hook_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &fails,
+                &[],
+                "hook",
+                None,
+            )
+            .unwrap()
+        });
+
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        // Only the admin registers hooks, each of them once.
+        let add_hook = |addr: &Addr| ExecuteMsg::AddHook {
+            addr: addr.to_string(),
+            gas_limit: None,
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &add_hook(&hook_addr),
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::Unauthorized {}
+        );
+        for hook in [&hook_addr, &failing_hook_addr] {
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &add_hook(hook),
+                &[],
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &add_hook(&hook_addr),
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::HookAlreadyRegistered {}
+        );
+        let hooks: HooksResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::Hooks {})
+            .unwrap();
+        assert_eq!(
+            hooks.hooks,
+            vec![
+                Hook {
+                    addr: hook_addr.clone(),
+                    gas_limit: DEFAULT_HOOK_GAS_LIMIT,
+                },
+                Hook {
+                    addr: failing_hook_addr.clone(),
+                    gas_limit: DEFAULT_HOOK_GAS_LIMIT,
+                },
+            ]
+        );
+
+        // The failing hook doesn't get in the way of the round.
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+        let verification: VerifyDrawResponse = app
+            .wrap()
+            .query_wasm_smart(
+                lotto_contract_addr.clone(),
+                &QueryMsg::VerifyDraw { round: 1 },
+            )
+            .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+
+        let notifications: Vec<LotteryHookMsg> =
+            app.wrap().query_wasm_smart(&hook_addr, &Empty {}).unwrap();
+        assert_eq!(
+            notifications,
+            vec![
+                LotteryHookMsg::TicketsPurchased {
+                    round: 1,
+                    purchase_id: 1,
+                    buyer: Addr::unchecked(TEST_USER_1),
+                    recipient: Addr::unchecked(TEST_USER_1),
+                    tickets: 2,
+                },
+                LotteryHookMsg::SalesClosed {
+                    round: 1,
+                    total_tickets: 2,
+                },
+                LotteryHookMsg::WinnerChosen {
+                    round: 1,
+                    winner: Addr::unchecked(TEST_USER_1),
+                    winning_ticket: verification.draw.winning_ticket,
+                },
+                LotteryHookMsg::PrizeClaimed {
+                    round: 1,
+                    winner: Addr::unchecked(TEST_USER_1),
+                    prize: coin(1_900, TESTING_NATIVE_DENOM),
+                },
+            ]
+        );
+
+        let remove_hook = ExecuteMsg::RemoveHook {
+            addr: failing_hook_addr.to_string(),
+        };
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &remove_hook,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &remove_hook,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::HookNotRegistered {}
+        );
+        let hooks: HooksResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::Hooks {})
+            .unwrap();
+        assert_eq!(
+            hooks.hooks,
+            vec![Hook {
+                addr: hook_addr,
+                gas_limit: DEFAULT_HOOK_GAS_LIMIT,
+            }]
+        );
+
+        // Every hook is notified in the same transaction, so there's a cap on them.
+        for hook in 1..MAX_HOOKS {
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::AddHook {
+                    addr: format!("hook{}", hook),
+                    gas_limit: Some(100_000),
+                },
+                &[],
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr,
+                &add_hook(&failing_hook_addr),
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::TooManyHooks { max: MAX_HOOKS }
+        );
+    }
+
+    #[test]
+    fn lottery_contract_helper_plays_a_round() {
+        let (mut app, lotto_code_id) = setup_app();
+        let instantiate_message = InstantiateMsg {
+            randomness: Some(RandomnessSource::AdminSeed {}),
+            seed_commitment: None,
+            ..testing_instantiate_msg()
+        };
+        let lottery = LotteryContract(
+            app.instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap(),
+        );
+
+        app.execute(
+            Addr::unchecked(TEST_USER_1),
+            lottery
+                .buy_ticket(2, None, None, vec![coin(2_000u128, TESTING_NATIVE_DENOM)])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            lottery
+                .ticket_count(&app.wrap(), Addr::unchecked(TEST_USER_1))
+                .unwrap()
+                .tickets,
+            Some(2)
+        );
+
+        app.update_block(expire(TESTING_DURATION));
+        app.execute(
+            Addr::unchecked(TEST_USER_2),
+            lottery.close_sales(vec![]).unwrap(),
+        )
+        .unwrap();
+        app.execute(
+            Addr::unchecked(TEST_ADMIN),
+            lottery
+                .execute_lottery(TESTING_SEED, testing_salt())
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(lottery.verify_draw(&app.wrap(), 1).unwrap().verified);
+        assert_eq!(
+            lottery.payout_preview(&app.wrap()).unwrap().winner_amount,
+            coin(1_900, TESTING_NATIVE_DENOM)
+        );
+        app.execute(
+            Addr::unchecked(TEST_USER_1),
+            lottery.claim_tokens().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            lottery.lottery_state(&app.wrap()).unwrap().lotto_state,
+            LotteryState::CLOSED {
+                winner: Addr::unchecked(TEST_USER_1),
+                claimed: true,
+            }
+        );
+        assert_eq!(lottery.stats(&app.wrap()).unwrap().tickets_sold, 2);
+        assert_eq!(
+            lottery.top_winners(&app.wrap(), None).unwrap().winners[0].addr,
+            Addr::unchecked(TEST_USER_1)
+        );
+    }
+
+    #[test]
+    fn cw20_snapshots_are_taken_over_several_calls() {
+        let (mut app, lotto_code_id) = setup_app();
+        let ticket_token_code_id = app.store_code(contract_ticket_token());
+
+        let instantiate_message = InstantiateMsg {
+            ticket_mode: Some(TicketMode::Cw20 {
+                code_id: ticket_token_code_id,
+                name: "Sphinx Ticket".to_string(),
+                symbol: "TICKET".to_string(),
+            }),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+        let ticket_token = app
+            .wrap()
+            .query_wasm_smart::<ConfigResponse>(lotto_contract_addr.clone(), &QueryMsg::Config {})
+            .unwrap()
+            .ticket_token
+            .unwrap();
+
+        // More holders than fit in a snapshot page
+        for holder in 0..35 {
+            app.execute_contract(
+                Addr::unchecked(TEST_GOD),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: Some(format!("holder{:02}", holder)),
+                    entropy: None,
+                },
+                &[coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)],
+            )
+            .unwrap();
+        }
+        app.update_block(expire(TESTING_DURATION));
+        let close_sales = |app: &mut App| {
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::CloseSales {},
+                &[],
+            )
+            .unwrap();
+            app.wrap()
+                .query_wasm_smart::<LotteryStateResponse>(
+                    lotto_contract_addr.clone(),
+                    &QueryMsg::LotteryState {},
+                )
+                .unwrap()
+                .lotto_state
+        };
+
+        // The first call reads holder00 to holder29.
+        assert!(matches!(close_sales(&mut app), LotteryState::OPEN { .. }));
+
+        // A ticket moved behind the pass would be counted twice, the pass starts over.
+        app.execute_contract(
+            Addr::unchecked("holder00"),
+            ticket_token,
+            &Cw20ExecuteMsg::Transfer {
+                recipient: "holder34".to_string(),
+                amount: Uint128::new(1u128),
+            },
+            &[],
+        )
+        .unwrap();
+        assert!(matches!(close_sales(&mut app), LotteryState::OPEN { .. }));
+
+        // A clean pass, then the ranges are assigned a page at a time.
+        assert!(matches!(close_sales(&mut app), LotteryState::OPEN { .. }));
+        assert!(matches!(close_sales(&mut app), LotteryState::OPEN { .. }));
+        assert_eq!(close_sales(&mut app), LotteryState::CHOOSING {});
+
+        let lottery_state: LotteryStateResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::LotteryState {})
+            .unwrap();
+        assert_eq!(lottery_state.total_tickets, 35);
+        for (holder, tickets) in [
+            ("holder00", None),
+            ("holder01", Some(1)),
+            ("holder34", Some(2)),
+        ] {
+            assert_eq!(
+                app.wrap()
+                    .query_wasm_smart::<TicketResponse>(
+                        lotto_contract_addr.clone(),
+                        &QueryMsg::TicketCount {
+                            addr: Addr::unchecked(holder),
+                        },
+                    )
+                    .unwrap(),
+                TicketResponse { tickets }
+            );
+        }
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr,
+                &ExecuteMsg::CloseSales {},
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::TicketBuyingNotAvailable {}
+        );
+    }
+
+    #[test]
+    fn griefed_cw20_snapshots_are_refunded_from_the_purchase_ledger() {
+        let (mut app, lotto_code_id) = setup_app();
+        let ticket_token_code_id = app.store_code(contract_ticket_token());
+
+        let instantiate_message = InstantiateMsg {
+            ticket_mode: Some(TicketMode::Cw20 {
+                code_id: ticket_token_code_id,
+                name: "Sphinx Ticket".to_string(),
+                symbol: "TICKET".to_string(),
+            }),
+            bond: Some(coin(1_000u128, TESTING_NATIVE_DENOM)),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+                "yolo",
+                None,
+            )
+            .unwrap();
+        let ticket_token = app
+            .wrap()
+            .query_wasm_smart::<ConfigResponse>(lotto_contract_addr.clone(), &QueryMsg::Config {})
+            .unwrap()
+            .ticket_token
+            .unwrap();
+
+        for holder in 0..35 {
+            app.execute_contract(
+                Addr::unchecked(TEST_GOD),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: Some(format!("holder{:02}", holder)),
+                    entropy: None,
+                },
+                &[coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)],
+            )
+            .unwrap();
+        }
+        app.update_block(expire(TESTING_DURATION));
+        let close_sales = |app: &mut App| {
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::CloseSales {},
+                &[],
+            )
+        };
+        let expire_draw = |app: &mut App| {
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExpireDraw {},
+                &[],
+            )
+        };
+
+        // Every pass, a ticket is moved from a holder already read to one that isn't.
+        for pass in 0..MAX_TICKET_SNAPSHOT_PASSES {
+            close_sales(&mut app).unwrap();
+            assert_eq!(
+                expire_draw(&mut app)
+                    .unwrap_err()
+                    .downcast::<ContractError>()
+                    .unwrap(),
+                ContractError::DrawDeadlineNotReached {}
+            );
+            app.execute_contract(
+                Addr::unchecked(format!("holder{:02}", pass)),
+                ticket_token.clone(),
+                &Cw20ExecuteMsg::Transfer {
+                    recipient: "holder34".to_string(),
+                    amount: Uint128::new(1u128),
+                },
+                &[],
+            )
+            .unwrap();
+            close_sales(&mut app).unwrap();
+        }
+
+        // The snapshot gives up, the round can be refunded right away.
+        assert_eq!(
+            close_sales(&mut app)
+                .unwrap_err()
+                .downcast::<ContractError>()
+                .unwrap(),
+            ContractError::TicketSnapshotFailed {}
+        );
+        let res = expire_draw(&mut app).unwrap();
+        let draw_expired = res
+            .events
+            .iter()
+            .find(|event| event.ty == "wasm-draw_expired")
+            .unwrap();
+        assert!(draw_expired
+            .attributes
+            .contains(&Attribute::new("refunds_owed", "35000ulotto")));
+        assert!(draw_expired
+            .attributes
+            .contains(&Attribute::new("bond_returned", "1000ulotto")));
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_ADMIN), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(10_000u128, TESTING_NATIVE_DENOM)
+        );
+
+        // Refunds go to whoever paid for the tickets, not to whoever holds them.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked("holder34"),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ClaimRefund { round: None },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::NothingToRefund {}
+        );
+        let god_balance = app
+            .wrap()
+            .query_balance(Addr::unchecked(TEST_GOD), TESTING_NATIVE_DENOM)
+            .unwrap()
+            .amount;
+        app.execute_contract(
+            Addr::unchecked(TEST_GOD),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: None },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_GOD), TESTING_NATIVE_DENOM)
+                .unwrap()
+                .amount,
+            god_balance + Uint128::new(35_000u128)
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&lotto_contract_addr, TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(0u128, TESTING_NATIVE_DENOM)
+        );
+
+        // Every refund is paid, the next round can start.
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr,
+            &ExecuteMsg::StartRound {
+                seed_commitment: Some(testing_seed_commitment()),
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn keepers_close_and_draw_rounds_for_a_bounty() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            keeper_bounty: Some(KeeperBounty::PoolShare(Decimal::percent(1))),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        for (user, num_tickets) in [(TEST_USER_1, 2u64), (TEST_USER_2, 1)] {
+            app.execute_contract(
+                Addr::unchecked(user),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient: None,
+                    entropy: None,
+                },
+                &[coin(
+                    TESTING_TICKET_COST * u128::from(num_tickets),
+                    TESTING_NATIVE_DENOM,
+                )],
+            )
+            .unwrap();
+        }
+        app.update_block(expire(TESTING_DURATION));
+
+        // 1% of the 3000 pool for closing sales.
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_3), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(30u128, TESTING_NATIVE_DENOM)
+        );
+
+        // The keeper doesn't need the admin key to reveal the committed seed, only the seed itself.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_3),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED + 1,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::SeedCommitmentMismatch {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+        // 1% of the remaining 2970 for drawing.
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_3), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(59u128, TESTING_NATIVE_DENOM)
+        );
+
+        let lottery_state: LotteryStateResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::LotteryState {})
+            .unwrap();
+        let winner = match lottery_state.lotto_state {
+            LotteryState::CLOSED { winner, .. } => winner,
+            lotto_state => panic!("unexpected state {:?}", lotto_state),
+        };
+        // The 2941 left pay 147 in fees and 2793 to the winner, rounding leaves 1 behind.
+        let preview: PayoutPreviewResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::PayoutPreview {})
+            .unwrap();
+        assert_eq!(preview.dust, coin(1u128, TESTING_NATIVE_DENOM));
+        app.execute_contract(
+            winner,
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(&lotto_contract_addr, TESTING_NATIVE_DENOM)
+                .unwrap(),
+            preview.dust
+        );
+    }
+
+    #[test]
+    fn coins_sent_to_the_contract_dont_grow_keeper_bounties() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            keeper_bounty: Some(KeeperBounty::PoolShare(Decimal::percent(10))),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+        app.send_tokens(
+            Addr::unchecked(TEST_GOD),
+            lotto_contract_addr.clone(),
+            &[coin(100_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        let keeper_balance = |app: &App| {
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_3), TESTING_NATIVE_DENOM)
+                .unwrap()
+                .amount
+                .u128()
+        };
+
+        // A round without tickets pays no bounty and can still be expired.
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        assert_eq!(keeper_balance(&app), 0);
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExpireDraw {},
+            &[],
+        )
+        .unwrap();
+
+        // With tickets, the bounty is 10% of what they paid.
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::StartRound {
+                seed_commitment: Some(testing_seed_commitment()),
+            },
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 2,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(TESTING_TICKET_COST * 2, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        assert_eq!(keeper_balance(&app), 200);
+
+        // The refund bears the bounty and nothing more.
+        app.update_block(expire(Duration::Time(DEFAULT_DRAW_DEADLINE_SECONDS)));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExpireDraw {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: None },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_1), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(4_800u128, TESTING_NATIVE_DENOM)
+        );
+    }
+
+    #[test]
+    fn the_bond_goes_back_to_the_admin_once_the_winner_claims() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            bond: Some(coin(2_000u128, TESTING_NATIVE_DENOM)),
+            ..testing_instantiate_msg()
+        };
+
+        // The first round's bond has to be sent with the instantiation.
+        assert_eq!(
+            app.instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+                "yolo",
+                None,
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::BondIncorrectAmount {
+                bond: format!("2000{}", TESTING_NATIVE_DENOM)
+            }
+        );
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[coin(2_000u128, TESTING_NATIVE_DENOM)],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExecuteLottery {
+                seed: TESTING_SEED,
+                salt: testing_salt(),
+            },
+            &[],
+        )
+        .unwrap();
+        // The preview is exactly what the claim pays out.
+        let preview: PayoutPreviewResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::PayoutPreview {})
+            .unwrap();
+        assert_eq!(
+            preview,
+            PayoutPreviewResponse {
+                round: 1,
+                gross_pool: coin(1_000u128, TESTING_NATIVE_DENOM),
+                fees: vec![FeeCut {
+                    recipient: Addr::unchecked(TEST_ADMIN),
+                    amount: coin(50u128, TESTING_NATIVE_DENOM),
+                }],
+                winner_amount: coin(950u128, TESTING_NATIVE_DENOM),
+                dust: coin(0u128, TESTING_NATIVE_DENOM),
+                bond: Some(coin(2_000u128, TESTING_NATIVE_DENOM)),
+            }
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimTokens {},
+            &[],
+        )
+        .unwrap();
+
+        // The winner only gets the ticket sales, the admin gets the fee and their bond back.
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_1), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(4_950u128, TESTING_NATIVE_DENOM)
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_ADMIN), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(10_050u128, TESTING_NATIVE_DENOM)
+        );
+        let bond: BondResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::Bond { round: Some(1) })
+            .unwrap();
+        assert_eq!(
+            bond.bond.map(|bond| bond.status),
+            Some(BondStatus::Returned {})
+        );
+    }
+
+    #[test]
+    fn players_are_refunded_with_the_bond_when_the_draw_deadline_passes() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            draw_deadline: Some(Duration::Time(3_600)),
+            bond: Some(coin(1_000u128, TESTING_NATIVE_DENOM)),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        for (user, num_tickets) in [(TEST_USER_1, 2u64), (TEST_USER_2, 1)] {
+            app.execute_contract(
+                Addr::unchecked(user),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets,
+                    recipient: None,
+                    entropy: None,
+                },
+                &[coin(
+                    TESTING_TICKET_COST * u128::from(num_tickets),
+                    TESTING_NATIVE_DENOM,
+                )],
+            )
+            .unwrap();
+        }
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        // The admin still has time to draw, so nobody can be refunded yet.
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExpireDraw {},
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::DrawDeadlineNotReached {}
+        );
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ClaimRefund { round: None },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RefundNotAvailable {}
+        );
+
+        // Past the deadline the admin can't reveal anymore, and anyone can open refunds.
+        // Not revealing costs the admin their bond, which is shared among the ticket holders.
+        app.update_block(expire(Duration::Time(3_600)));
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExecuteLottery {
+                    seed: TESTING_SEED,
+                    salt: testing_salt(),
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::DrawDeadlinePassed {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_3),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExpireDraw {},
+            &[],
+        )
+        .unwrap();
+        let bond: BondResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::Bond { round: None })
+            .unwrap();
+        assert_eq!(
+            bond,
+            BondResponse {
+                round: 1,
+                bond: Some(Bond {
+                    amount: coin(1_000u128, TESTING_NATIVE_DENOM),
+                    status: BondStatus::Slashed {},
+                }),
+            }
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: None },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_1), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(5_666u128, TESTING_NATIVE_DENOM)
+        );
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ClaimRefund { round: None },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RefundAlreadyClaimed {}
+        );
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_3),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ClaimRefund { round: None },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::NothingToRefund {}
+        );
+
+        // The next round can only start once every ticket has been refunded.
+        let start_round = ExecuteMsg::StartRound {
+            seed_commitment: Some(seed_commitment(7, b"round two")),
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &start_round,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RoundNotFinished {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: None },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_2), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(5_334u128, TESTING_NATIVE_DENOM)
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&lotto_contract_addr, TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(0u128, TESTING_NATIVE_DENOM)
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr,
+            &start_round,
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn unclaimed_refunds_stay_claimable_once_the_next_round_starts() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            draw_deadline: Some(Duration::Time(3_600)),
+            refund_window: Some(Duration::Time(86_400)),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        for user in [TEST_USER_1, TEST_USER_2] {
+            app.execute_contract(
+                Addr::unchecked(user),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::BuyTicket {
+                    num_tickets: 1,
+                    recipient: None,
+                    entropy: None,
+                },
+                &[coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)],
+            )
+            .unwrap();
+        }
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+        app.update_block(expire(Duration::Time(3_600)));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ExpireDraw {},
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: None },
+            &[],
+        )
+        .unwrap();
+
+        // A player that never claims only blocks the next round for the refund window.
+        let start_round = ExecuteMsg::StartRound {
+            seed_commitment: Some(seed_commitment(7, b"round two")),
+        };
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_ADMIN),
+                lotto_contract_addr.clone(),
+                &start_round,
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RoundNotFinished {}
+        );
+        app.update_block(expire(Duration::Time(86_400)));
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &start_round,
+            &[],
+        )
+        .unwrap();
+
+        // The refund still owed isn't part of the next round's pool.
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::BuyTicket {
+                num_tickets: 1,
+                recipient: None,
+                entropy: None,
+            },
+            &[coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        let payout: PayoutPreviewResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::PayoutPreview {})
+            .unwrap();
+        assert_eq!(
+            payout.gross_pool,
+            coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)
+        );
+
+        assert_eq!(
+            app.execute_contract(
+                Addr::unchecked(TEST_USER_2),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ClaimRefund { round: None },
+                &[],
+            )
+            .unwrap_err()
+            .downcast::<ContractError>()
+            .unwrap(),
+            ContractError::RefundNotAvailable {}
+        );
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_2),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::ClaimRefund { round: Some(1) },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_USER_2), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(5_000u128, TESTING_NATIVE_DENOM)
+        );
+        let payout: PayoutPreviewResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::PayoutPreview {})
+            .unwrap();
+        assert_eq!(
+            payout.gross_pool,
+            coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM)
+        );
+    }
+
+    #[test]
+    fn rounds_without_tickets_return_the_bond() {
+        let (mut app, lotto_code_id) = setup_app();
+
+        let instantiate_message = InstantiateMsg {
+            bond: Some(coin(1_000u128, TESTING_NATIVE_DENOM)),
+            ..testing_instantiate_msg()
+        };
+
+        let lotto_contract_addr = app
+            .instantiate_contract(
+                lotto_code_id,
+                Addr::unchecked(TEST_ADMIN),
+                &instantiate_message,
+                &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+                "yolo",
+                None,
+            )
+            .unwrap();
+
+        app.update_block(expire(TESTING_DURATION));
+        app.execute_contract(
+            Addr::unchecked(TEST_USER_1),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::CloseSales {},
+            &[],
+        )
+        .unwrap();
+
+        // Nothing to draw, so there's no need to wait for the draw deadline.
+        let res = app
+            .execute_contract(
+                Addr::unchecked(TEST_USER_1),
+                lotto_contract_addr.clone(),
+                &ExecuteMsg::ExpireDraw {},
+                &[],
+            )
+            .unwrap();
+        let draw_expired = res
+            .events
+            .iter()
+            .find(|event| event.ty == "wasm-draw_expired")
+            .unwrap();
+        assert!(draw_expired
+            .attributes
+            .contains(&Attribute::new("bond_returned", "1000ulotto")));
+        let bond: BondResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr.clone(), &QueryMsg::Bond { round: None })
+            .unwrap();
+        assert_eq!(bond.bond.unwrap().status, BondStatus::Returned {},);
+        assert_eq!(
+            app.wrap()
+                .query_balance(Addr::unchecked(TEST_ADMIN), TESTING_NATIVE_DENOM)
+                .unwrap(),
+            coin(10_000u128, TESTING_NATIVE_DENOM)
+        );
+
+        app.execute_contract(
+            Addr::unchecked(TEST_ADMIN),
+            lotto_contract_addr.clone(),
+            &ExecuteMsg::StartRound {
+                seed_commitment: Some(seed_commitment(7, b"round two")),
+            },
+            &[coin(1_000u128, TESTING_NATIVE_DENOM)],
+        )
+        .unwrap();
+        let payout: PayoutPreviewResponse = app
+            .wrap()
+            .query_wasm_smart(lotto_contract_addr, &QueryMsg::PayoutPreview {})
+            .unwrap();
+        assert_eq!(payout.gross_pool, coin(0u128, TESTING_NATIVE_DENOM));
+    }
+
+    // The draw deadline of a round whose sales closed in the current block
+    fn draw_deadline_from_now(app: &App) -> Option<Expiration> {
+        Some(Expiration::AtTime(
+            app.block_info()
+                .time
+                .plus_seconds(DEFAULT_DRAW_DEADLINE_SECONDS),
+        ))
+    }
+
     fn setup_app() -> (App, u64) {
         // God is genesis, the whole defined sentient
         let mut app = mock_app(
@@ -1,9 +1,10 @@
 pub mod constants;
 pub mod contract;
 pub mod error;
+pub mod events;
 pub mod helpers;
+pub mod ibc;
 pub mod integration_tests;
-pub mod models;
 pub mod msg;
 pub mod state;
 pub mod test_util;
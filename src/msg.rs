@@ -1,22 +1,100 @@
-use crate::state::{Config, LotteryState};
+use crate::state::{
+    Bond, Config, DrawRecord, Hook, KeeperBounty, LotteryState, ParticipantEntropy, Purchase,
+    RandomnessRequestStatus, RandomnessSource, TicketMode, Winnings,
+};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_schema::QueryResponses;
-use cosmwasm_std::{Addr, Coin};
-use cw_utils::Duration;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, Decimal, HexBinary, StdResult, Timestamp, Uint128,
+    WasmMsg,
+};
+use cw_utils::{Duration, Expiration};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub ticket_cost: Coin,
     pub lottery_duration: Duration,
+    // How long the winner can be drawn for once sales close, 3 days when omitted.
+    pub draw_deadline: Option<Duration>,
+    // How long players have to claim refunds before the admin can start the next round,
+    // 30 days when omitted. Unclaimed refunds stay claimable afterwards.
+    pub refund_window: Option<Duration>,
+    // Bond the admin posts with every round, sent along with this message for the first one.
+    pub bond: Option<Coin>,
+    // Paid out of the pool to whoever closes sales or draws a round, none when omitted.
+    pub keeper_bounty: Option<KeeperBounty>,
     pub admin: String,
     pub house_fee: u64,
+    // Defaults to TicketMode::Native when omitted.
+    pub ticket_mode: Option<TicketMode>,
+    // Defaults to RandomnessSource::CommitReveal when omitted.
+    pub randomness: Option<RandomnessSource>,
+    // sha256(seed || salt) for the first round, revealed through ExecuteLottery.
+    // With RandomnessSource::OperatorSignature, the first 32 bytes of the operator's signature
+    // for the round instead. Not used by other sources.
+    pub seed_commitment: Option<HexBinary>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    BuyTicket { num_tickets: u64 },
-    ExecuteLottery { seed: u64 },
+    BuyTicket {
+        num_tickets: u64,
+        // Gift the tickets to another address instead of the buyer.
+        recipient: Option<String>,
+        // Mixed into the round's participant entropy, which is hashed into the draw.
+        entropy: Option<HexBinary>,
+    },
+    TransferTickets {
+        recipient: String,
+        amount: u64,
+    },
+    ExecuteLottery {
+        seed: u64,
+        salt: HexBinary,
+    },
     ClaimTokens {},
+    CloseSales {},
+    // Sent along with the configured bond, if any.
+    StartRound {
+        seed_commitment: Option<HexBinary>,
+    },
+    // Anyone can submit the beacon of the drand round fixed when sales closed.
+    SubmitDrandBeacon {
+        signature: HexBinary,
+        previous_signature: HexBinary,
+    },
+    // Sends the randomness request packet again after a timeout or a rejection.
+    RequestRandomness {},
+    // Anyone can relay the operator's signature over the round's OperatorMessage.
+    SubmitOperatorSignature {
+        signature: HexBinary,
+    },
+    // Callback of the randomness oracle, only accepted from the configured contract.
+    // Named after the Nois proxy callback so a Nois proxy can be used as the oracle.
+    NoisReceive {
+        callback: OracleCallback,
+    },
+    // Anyone can move a round that wasn't drawn before its deadline, or sold no tickets,
+    // to REFUNDING.
+    ExpireDraw {},
+    // Pays back the sender's tickets of a round that missed its draw deadline, the current
+    // one when omitted.
+    ClaimRefund {
+        round: Option<u64>,
+    },
+    // Switches the source used from the next round on, once the current round is drawn.
+    SetRandomnessSource {
+        randomness: RandomnessSource,
+    },
+    // Registers a contract to be sent a LotteryHookMsg on every purchase, close, draw and claim.
+    // Each notification can use up to `gas_limit`, DEFAULT_HOOK_GAS_LIMIT when omitted.
+    AddHook {
+        addr: String,
+        gas_limit: Option<u64>,
+    },
+    RemoveHook {
+        addr: String,
+    },
 }
 
 #[cw_serde]
@@ -26,8 +104,64 @@ pub enum QueryMsg {
     TicketCount { addr: Addr },
     #[returns(LotteryStateResponse)]
     LotteryState {}, // not just the lottery state but more
+    // The lifecycle phase at the current block, whether or not the stored state caught up yet
+    #[returns(StatusResponse)]
+    Status {},
     #[returns(ConfigResponse)]
     Config {},
+    #[returns(HooksResponse)]
+    Hooks {},
+    // Players of a round and their tickets, ordered by address. Defaults to the current round.
+    #[returns(PlayersResponse)]
+    Players {
+        round: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(RandomnessRequestResponse)]
+    RandomnessRequest {},
+    #[returns(ParticipantEntropyResponse)]
+    ParticipantEntropy {},
+    // Every round an address took part in, ordered by round
+    #[returns(PlayerHistoryResponse)]
+    PlayerHistory {
+        addr: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // A round's ticket purchases (the current one by default), ordered by purchase id
+    #[returns(PurchasesResponse)]
+    Purchases {
+        round: Option<u64>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(PurchaseResponse)]
+    Purchase { id: u64 },
+    // Lifetime counters across all rounds
+    #[returns(StatsResponse)]
+    Stats {},
+    // Addresses that won the most over all rounds, largest first
+    #[returns(TopWinnersResponse)]
+    TopWinners { limit: Option<u32> },
+    // An address's chances of winning the current round
+    #[returns(OddsResponse)]
+    Odds { addr: String },
+    // What buying more tickets in the current round would cost and change
+    #[returns(SimulatePurchaseResponse)]
+    SimulatePurchase { addr: String, num_tickets: u64 },
+    // The message the operator signs for the current round, once sales are closed
+    #[returns(OperatorMessageResponse)]
+    OperatorMessage {},
+    // How the current round's pool would be paid out if the winner claimed now
+    #[returns(PayoutPreviewResponse)]
+    PayoutPreview {},
+    // The admin's bond of a round, the current one when omitted
+    #[returns(BondResponse)]
+    Bond { round: Option<u64> },
+    // Recomputes the winning ticket of a past round from its stored inputs
+    #[returns(VerifyDrawResponse)]
+    VerifyDraw { round: u64 },
 }
 
 #[cw_serde]
@@ -35,13 +169,245 @@ pub struct TicketResponse {
     pub tickets: Option<u64>,
 }
 
+#[cw_serde]
+pub struct PlayerTickets {
+    pub addr: Addr,
+    pub tickets: u64,
+}
+
+#[cw_serde]
+pub struct PlayersResponse {
+    pub round: u64,
+    pub players: Vec<PlayerTickets>,
+}
+
+#[cw_serde]
+pub struct PlayerRound {
+    pub round: u64,
+    pub tickets: u64, // Held in the round, snapshotted at close for cw20 tickets
+    pub tickets_bought: u64,
+    pub spent: Coin,
+    pub won: bool,
+    pub claimed: bool,
+    pub refunded: Option<Uint128>, // Set once a refund was claimed for the round
+}
+
+#[cw_serde]
+pub struct PlayerHistoryResponse {
+    pub addr: Addr,
+    pub rounds: Vec<PlayerRound>,
+}
+
+#[cw_serde]
+pub struct PurchasesResponse {
+    pub round: u64,
+    pub purchases: Vec<Purchase>,
+}
+
+#[cw_serde]
+pub struct PurchaseResponse {
+    pub purchase: Purchase,
+}
+
+#[cw_serde]
+pub struct StatsResponse {
+    pub tickets_sold: u64,
+    pub volume: Coin,
+    pub fees_paid: Coin,
+    pub largest_jackpot: Coin,
+    pub unique_players: u64,
+}
+
+#[cw_serde]
+pub struct TopWinnersResponse {
+    pub winners: Vec<Winnings>,
+}
+
+#[cw_serde]
+pub struct OddsResponse {
+    pub round: u64,
+    pub tickets: u64,
+    pub total_tickets: u64,
+    pub win_probability: Decimal, // tickets / total_tickets, zero when nobody entered
+}
+
+#[cw_serde]
+pub struct SimulatePurchaseResponse {
+    pub cost: Coin,
+    pub odds: OddsResponse, // Once the tickets are bought
+    // What the winner would get from the pool once the tickets are bought, after the house fee
+    pub projected_prize: Coin,
+}
+
 #[cw_serde]
 pub struct LotteryStateResponse {
     pub lotto_state: LotteryState,
     pub total_tickets: u64,
+    pub round: u64,
+    pub drand_round: Option<u64>,
+    pub draw_deadline: Option<Expiration>, // Set once sales close
+}
+
+#[cw_serde]
+pub enum Phase {
+    // Tickets can be bought
+    Open {},
+    // Sales are over but haven't been closed yet
+    SalesEnded {},
+    // Waiting for the round's randomness
+    Choosing {},
+    // The draw deadline passed, the round can be moved to refunds
+    DrawExpired {},
+    // The winner has been drawn and can claim
+    Drawn {},
+    // The winner claimed, the next round can start
+    Claimed {},
+    Refunding {},
+}
+
+#[cw_serde]
+pub struct StatusResponse {
+    pub round: u64,
+    pub phase: Phase,
+    // When the current phase ends: the end of sales, or the draw deadline while choosing
+    pub ends_at: Option<Expiration>,
+    pub seconds_remaining: Option<u64>, // Set when the phase ends at a time
+    pub blocks_remaining: Option<u64>,  // Set when the phase ends at a height
+    // Whether a draw message would be accepted right now
+    pub draw_executable: bool,
+    // Names of the execute messages that can be sent right now, e.g. "buy_ticket"
+    pub valid_actions: Vec<String>,
 }
 
 #[cw_serde]
 pub struct ConfigResponse {
     pub config: Config,
+    pub ticket_token: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct HooksResponse {
+    pub hooks: Vec<Hook>,
+}
+
+// Sent to every hook contract, which has to accept it as the `lottery_hook` variant of its
+// execute message.
+#[cw_serde]
+pub enum LotteryHookMsg {
+    TicketsPurchased {
+        round: u64,
+        purchase_id: u64,
+        buyer: Addr,
+        recipient: Addr,
+        tickets: u64,
+    },
+    SalesClosed {
+        round: u64,
+        total_tickets: u64,
+    },
+    WinnerChosen {
+        round: u64,
+        winner: Addr,
+        winning_ticket: u64,
+    },
+    PrizeClaimed {
+        round: u64,
+        winner: Addr,
+        prize: Coin,
+    },
+}
+
+impl LotteryHookMsg {
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg: to_binary(&LotteryHookExecuteMsg::LotteryHook(self))?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+enum LotteryHookExecuteMsg {
+    LotteryHook(LotteryHookMsg),
+}
+
+#[cw_serde]
+pub struct RandomnessRequestResponse {
+    pub channel: Option<String>,
+    pub status: Option<RandomnessRequestStatus>,
+}
+
+#[cw_serde]
+pub struct ParticipantEntropyResponse {
+    pub round: u64,
+    // None until a buyer contributes entropy to the round
+    pub participant_entropy: Option<ParticipantEntropy>,
+}
+
+#[cw_serde]
+pub struct OperatorMessageResponse {
+    pub round: u64,
+    pub ledger_hash: HexBinary,
+    pub message: HexBinary, // sha256 digest, signed as is with either scheme
+}
+
+#[cw_serde]
+pub struct FeeCut {
+    pub recipient: Addr,
+    pub amount: Coin,
+}
+
+#[cw_serde]
+pub struct PayoutPreviewResponse {
+    pub round: u64,
+    // The contract's balance in the ticket denom, without the bond, the refunds owed and the
+    // keeper bounties still to be paid for closing sales and drawing
+    pub gross_pool: Coin,
+    pub fees: Vec<FeeCut>,
+    pub winner_amount: Coin,
+    pub dust: Coin, // Left by rounding the shares down, it goes to the next round's pool
+    pub bond: Option<Coin>, // Sent back to the admin along with the payout
+}
+
+#[cw_serde]
+pub struct BondResponse {
+    pub round: u64,
+    pub bond: Option<Bond>, // None when no bond was required for the round
+}
+
+#[cw_serde]
+pub struct VerifyDrawResponse {
+    pub draw: DrawRecord,
+    pub winning_ticket: u64, // Recomputed from the draw's entropy, round and total tickets
+    pub verified: bool,
+}
+
+// Job request sent to the randomness oracle, the same shape as a Nois proxy's
+#[cw_serde]
+pub enum OracleExecuteMsg {
+    GetNextRandomness { job_id: String },
+}
+
+#[cw_serde]
+pub struct OracleCallback {
+    pub job_id: String,
+    pub published: Timestamp,
+    pub randomness: HexBinary,
+}
+
+// Packets exchanged with the randomness provider over IBC
+#[cw_serde]
+pub enum RandomnessPacket {
+    // Sent by the lottery when sales close
+    Request { round: u64 },
+    // Sent back by the provider with 32 bytes of randomness for the round
+    Beacon { round: u64, randomness: HexBinary },
+}
+
+#[cw_serde]
+pub enum RandomnessAck {
+    Result(Binary),
+    Error(String),
 }
@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Decimal};
-use cw_storage_plus::{Item, Map};
-use cw_utils::Expiration;
+use cosmwasm_std::{Addr, Coin, Decimal, HexBinary, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::{Duration, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -10,16 +10,366 @@ pub struct Config {
     pub admin: Addr,
     pub house_fee: Decimal,
     pub ticket_unit_cost: Coin, // The cost per ticket. can be native token, juno or what have you.
+    pub ticket_mode: TicketMode,
+    pub lottery_duration: Duration, // How long ticket sales stay open for every round
+    pub draw_deadline: Duration,    // How long the winner can be drawn for once sales close
+    pub refund_window: Duration,    // How long refunds block the next round once a draw expires
+    pub bond: Option<Coin>,         // Posted by the admin for every round, in the ticket denom
+    pub keeper_bounty: Option<KeeperBounty>, // Paid to whoever closes sales or draws a round
+    pub randomness: RandomnessSource, // Where the entropy for each round's draw comes from
+}
+
+// Paid out of the round's ticket payments, less the bounties already paid, in the ticket denom.
+#[cw_serde]
+pub enum KeeperBounty {
+    // A share of the pool at the time of the call, e.g. 0.001 for 0.1%
+    PoolShare(Decimal),
+    // A fixed amount, capped at the pool
+    Fixed(Uint128),
+}
+
+// Every source ends up providing 32 bytes of entropy to the same draw.
+#[cw_serde]
+pub enum RandomnessSource {
+    // The admin picks the seed when executing the lottery, players have to trust them.
+    AdminSeed {},
+    // The admin reveals a seed committed to before the round's tickets are sold.
+    CommitReveal {},
+    // Anyone submits the beacon of the drand round fixed when sales close.
+    Drand(DrandConfig),
+    // A provider chain sends the randomness over IBC once sales close.
+    Ibc(IbcRandomnessConfig),
+    // The operator signs the round and its ticket ledger once sales close.
+    OperatorSignature(OperatorSignatureConfig),
+    // A randomness oracle contract, e.g. a Nois proxy, is asked for randomness when sales
+    // close and calls back with it.
+    Oracle(OracleConfig),
+}
+
+#[cw_serde]
+pub struct OracleConfig {
+    pub contract: Addr,
+    // Sent along with every job request, passed through from the CloseSales caller
+    pub fee: Option<Coin>,
+}
+
+#[cw_serde]
+pub struct OperatorSignatureConfig {
+    pub scheme: SignatureScheme,
+    pub pubkey: HexBinary,
+}
+
+#[cw_serde]
+pub enum SignatureScheme {
+    // Compressed (33 bytes) or uncompressed (65 bytes) public key
+    Secp256k1 {},
+    // 32 bytes public key
+    Ed25519 {},
+}
+
+#[cw_serde]
+pub struct DrandConfig {
+    // G1 public key of a chained drand network, e.g. the League of Entropy mainnet
+    pub pubkey: HexBinary,
+    pub genesis_time: Timestamp,
+    pub period: u64, // Seconds between two drand rounds
+    // Rounds skipped past the next one when sales close, so that the drawn beacon can't be
+    // published before the close is final. DEFAULT_DRAND_SAFETY_MARGIN when omitted.
+    pub safety_margin: Option<u64>,
+}
+
+#[cw_serde]
+pub enum TicketMode {
+    // Tickets only live in this contract's PLAYERS map.
+    Native {},
+    // Tickets are minted as a cw20 token so they can be traded or pooled while sales are open.
+    // Balances are snapshotted into PLAYERS when sales close.
+    Cw20 {
+        code_id: u64,
+        name: String,
+        symbol: String,
+    },
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
-// Map of players and their ticket allocation
-pub const PLAYERS: Map<Addr, u64> = Map::new("players");
+// The cw20 ticket token, only set when running in TicketMode::Cw20
+pub const TICKET_TOKEN: Item<Addr> = Item::new("ticket_token");
+
+// Progress of the cw20 ticket snapshot, taken over as many CloseSales calls as it takes
+#[cw_serde]
+pub enum TicketSnapshot {
+    // Holders' balances are being read, `counted` sums the ones read by the current pass.
+    Reading {
+        pass: u32,
+        start_after: Option<String>,
+        counted: Uint128,
+    },
+    // The pass added up to the token's supply, its balances are turned into ticket ranges.
+    Assigning {
+        pass: u32,
+        start_after: Option<Addr>,
+    },
+    // No pass added up within MAX_TICKET_SNAPSHOT_PASSES, the round can only be refunded.
+    Failed {},
+}
+
+pub const TICKET_SNAPSHOT: Item<TicketSnapshot> = Item::new("ticket_snapshot");
+
+// Balances read by the snapshot passes as (pass, tickets), keyed by round and holder
+pub const SNAPSHOT_BALANCES: Map<(u64, Addr), (u32, u64)> = Map::new("snapshot_balances");
+
+// Map of players and their ticket allocation, keyed by round
+pub const PLAYERS: Map<(u64, Addr), u64> = Map::new("players");
+
+// sha256 chain over the current round's ticket purchases and transfers, see util::ledger_hash.
+// It is frozen once sales close, which makes it a snapshot of who holds which tickets.
+pub const TICKET_LEDGER_HASH: Item<HexBinary> = Item::new("ticket_ledger_hash");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Participation {
+    pub tickets_bought: u64, // Paid for by the address, whoever they went to
+    pub spent: Uint128,      // In the ticket denom
+}
+
+// Every round an address bought or held tickets in, keyed by address then round
+pub const PARTICIPATIONS: Map<(Addr, u64), Participation> = Map::new("participations");
+
+#[cw_serde]
+pub struct Purchase {
+    pub id: u64,
+    pub round: u64,
+    pub buyer: Addr,
+    pub recipient: Addr, // The buyer unless the tickets were bought for someone else
+    pub tickets: u64,
+    pub paid: Coin,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+pub struct PurchaseIndexes<'a> {
+    pub round: MultiIndex<'a, u64, Purchase, u64>,
+}
+
+impl<'a> IndexList<Purchase> for PurchaseIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Purchase>> + '_> {
+        let v: Vec<&dyn Index<Purchase>> = vec![&self.round];
+        Box::new(v.into_iter())
+    }
+}
+
+// Ledger of every ticket purchase, keyed by id and indexed by round
+pub fn purchases<'a>() -> IndexedMap<'a, u64, Purchase, PurchaseIndexes<'a>> {
+    let indexes = PurchaseIndexes {
+        round: MultiIndex::new(
+            |_pk, purchase| purchase.round,
+            "purchases",
+            "purchases__round",
+        ),
+    };
+    IndexedMap::new("purchases", indexes)
+}
+
+// Number of purchases made so far, the last purchase id
+pub const PURCHASE_COUNT: Item<u64> = Item::new("purchase_count");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Stats {
+    pub tickets_sold: u64,
+    pub volume: Uint128,          // Paid for tickets, in the ticket denom
+    pub fees_paid: Uint128,       // House fees paid to the admin
+    pub largest_jackpot: Uint128, // The largest prize claimed by a winner
+    pub unique_players: u64,      // Addresses that bought or held tickets in any round
+}
+
+// Lifetime counters across all rounds
+pub const STATS: Item<Stats> = Item::new("stats");
+
+#[cw_serde]
+pub struct Winnings {
+    pub addr: Addr,
+    pub total_won: Uint128,
+    pub wins: u64,
+}
+
+pub struct WinningsIndexes<'a> {
+    pub total_won: MultiIndex<'a, u128, Winnings, Addr>,
+}
+
+impl<'a> IndexList<Winnings> for WinningsIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Winnings>> + '_> {
+        let v: Vec<&dyn Index<Winnings>> = vec![&self.total_won];
+        Box::new(v.into_iter())
+    }
+}
+
+// What every winner claimed over all rounds, indexed by amount for the leaderboard
+pub fn winnings<'a>() -> IndexedMap<'a, Addr, Winnings, WinningsIndexes<'a>> {
+    let indexes = WinningsIndexes {
+        total_won: MultiIndex::new(
+            |_pk, winnings| winnings.total_won.u128(),
+            "winnings",
+            "winnings__total_won",
+        ),
+    };
+    IndexedMap::new("winnings", indexes)
+}
+
+// Total number of tickets in each round, kept next to the ticket ranges
+pub const TOTAL_TICKETS: Map<u64, u64> = Map::new("total_tickets");
+
+// Tickets paid for in each round, whoever they went to. In cw20 mode they only get ranges
+// once sales close, so this can be ahead of TOTAL_TICKETS until then.
+pub const TICKETS_SOLD: Map<u64, u64> = Map::new("tickets_sold");
+
+#[cw_serde]
+pub struct TicketRange {
+    pub round: u64,
+    pub owner: Addr,
+    pub start: u64, // First ticket index of the range, the key holds the end (exclusive)
+}
+
+pub struct TicketRangeIndexes<'a> {
+    pub owner: MultiIndex<'a, (u64, Addr), TicketRange, (u64, u64)>,
+}
+
+impl<'a> IndexList<TicketRange> for TicketRangeIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TicketRange>> + '_> {
+        let v: Vec<&dyn Index<TicketRange>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+// Ledger of the ticket index ranges of every round, keyed by (round, cumulative end).
+// Purchases append a range at the end, so the owner of a ticket index is the first range
+// ending after it. Transfers hand over or split the sender's ranges in place.
+pub fn ticket_ranges<'a>() -> IndexedMap<'a, (u64, u64), TicketRange, TicketRangeIndexes<'a>> {
+    let indexes = TicketRangeIndexes {
+        owner: MultiIndex::new(
+            |_pk, range| (range.round, range.owner.clone()),
+            "ticket_ranges",
+            "ticket_ranges__owner",
+        ),
+    };
+    IndexedMap::new("ticket_ranges", indexes)
+}
+
+// The round currently being played, starting at 1 on instantiation
+pub const ROUND: Item<u64> = Item::new("lotto_round");
+
+// sha256(seed || salt) committed by the admin before the round's tickets are sold
+pub const SEED_COMMITMENT: Item<HexBinary> = Item::new("seed_commitment");
+
+// The drand round whose beacon decides the draw, fixed when sales close
+pub const DRAND_ROUND: Item<u64> = Item::new("drand_round");
+
+#[cw_serde]
+pub struct IbcRandomnessConfig {
+    // Only channels on this connection, to the randomness provider's chain, are accepted
+    pub connection_id: String,
+    pub timeout_seconds: u64, // How long a randomness request packet has to be relayed
+}
+
+// The channel to the randomness provider, set once the handshake completes
+pub const RANDOMNESS_CHANNEL: Item<String> = Item::new("randomness_channel");
+
+#[cw_serde]
+pub enum RandomnessRequestStatus {
+    // The request packet has been sent but not acknowledged yet
+    Pending {},
+    // The provider accepted the request, its beacon packet will trigger the draw
+    Accepted {},
+    // The request timed out or was rejected, it can be sent again
+    Failed {},
+}
+
+// Status of the current round's randomness request
+pub const RANDOMNESS_REQUEST: Item<RandomnessRequestStatus> = Item::new("randomness_request");
+
+#[cw_serde]
+pub struct DrawRecord {
+    pub version: u32,              // The derivation used, see util::winning_ticket
+    pub source_entropy: HexBinary, // Provided by the configured randomness source
+    pub participant_entropy: Option<HexBinary>, // The round's final accumulator, if any
+    pub entropy: HexBinary,        // What the draw used, see util::mix_entropy
+    pub total_tickets: u64,
+    pub winning_ticket: u64,
+    pub winner: Addr,
+}
+
+// Inputs and result of every round's draw, keyed by round
+pub const DRAWS: Map<u64, DrawRecord> = Map::new("draws");
+
+// Past it the round can no longer be drawn and players get refunded, set when sales close
+pub const DRAW_DEADLINE: Item<Expiration> = Item::new("draw_deadline");
+
+#[cw_serde]
+pub struct Bond {
+    pub amount: Coin,
+    pub status: BondStatus,
+}
+
+#[cw_serde]
+pub enum BondStatus {
+    // Held by the contract while the round is played
+    Posted {},
+    // Paid back to the admin along with the house fee once the winner claims
+    Returned {},
+    // The round missed its draw deadline, the bond is shared among the ticket holders
+    Slashed {},
+}
+
+// The admin's bond of every round, keyed by round
+pub const BONDS: Map<u64, Bond> = Map::new("bonds");
+
+// Bounties paid out of each round's pool to the keepers that closed sales or drew it
+pub const KEEPER_BOUNTIES: Map<u64, Uint128> = Map::new("keeper_bounties");
+
+// What's left to refund of every round that missed its draw deadline, keyed by round
+#[cw_serde]
+pub struct RefundPool {
+    pub outstanding: Uint128, // In the ticket denom
+    pub total_tickets: u64,
+    pub refunded_tickets: u64,
+    // Sales were never closed, tickets are refunded to whoever paid for them instead of to
+    // whoever held them at close.
+    pub from_purchases: bool,
+    // Past it the admin can start the next round, the remaining refunds stay claimable.
+    pub claim_deadline: Expiration,
+}
+
+pub const REFUND_POOLS: Map<u64, RefundPool> = Map::new("refund_pools");
+
+// Refunds still owed for past rounds, held back from the current round's pool
+pub const RESERVED_REFUNDS: Item<Uint128> = Item::new("reserved_refunds");
+
+// Ticket payments and bond shares refunded to each player, keyed by round
+pub const REFUNDS: Map<(u64, Addr), Uint128> = Map::new("refunds");
+
+// A contract notified of purchases, closes, draws and claims, see msg::LotteryHookMsg
+#[cw_serde]
+pub struct Hook {
+    pub addr: Addr,
+    pub gas_limit: u64, // Gas each notification can use, past it only the notification fails
+}
+
+pub const HOOKS: Item<Vec<Hook>> = Item::new("hooks");
 
 // Current state of the ongoing lottery
 pub const LOTTERY_STATE: Item<LotteryState> = Item::new("lotto_state");
 
+#[cw_serde]
+pub struct ParticipantEntropy {
+    // sha256 chain over every contribution of the round, see util::accumulate_entropy
+    pub accumulator: HexBinary,
+    pub contributions: u64,
+}
+
+// Entropy contributed by the current round's buyers
+pub const PARTICIPANT_ENTROPY: Item<ParticipantEntropy> = Item::new("participant_entropy");
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub enum LotteryState {
     OPEN {
@@ -32,6 +382,10 @@ pub enum LotteryState {
         winner: Addr,
         claimed: bool,
     },
+    // The round wasn't drawn before its deadline, every player can claim back their tickets.
+    REFUNDING {
+        refunded_tickets: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
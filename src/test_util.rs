@@ -1,12 +1,99 @@
 #[cfg(test)]
 pub mod tests {
-    use cosmwasm_std::Coin;
+    use cosmwasm_std::{coin, Coin, HexBinary, Timestamp};
+    use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+    use curve25519_dalek::scalar::Scalar;
     use cw_utils::Duration;
+    use sha2::{Digest, Sha512};
+
+    use crate::msg::InstantiateMsg;
+    use crate::state::DrandConfig;
+    use crate::util::seed_commitment;
 
     pub const TESTING_NATIVE_DENOM: &str = "ulotto";
     pub const TESTING_TICKET_COST: u128 = 1_000_u128;
     pub const TESTING_1_WEEK_IN_SECONDS: u64 = 604_800u64;
     pub const TESTING_DURATION: Duration = Duration::Time(TESTING_1_WEEK_IN_SECONDS);
+    pub const TESTING_SEED: u64 = 12;
+    pub const TESTING_SALT: &[u8] = b"sphinx";
+
+    // League of Entropy mainnet (curl -sS https://drand.cloudflare.com/info)
+    pub const TESTING_DRAND_PUBKEY: &str = "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31";
+    pub const TESTING_DRAND_GENESIS_TIME: u64 = 1595431050;
+    pub const TESTING_DRAND_PERIOD: u64 = 30;
+    // curl -sS https://drand.cloudflare.com/public/72785
+    pub const TESTING_DRAND_ROUND: u64 = 72785;
+    pub const TESTING_DRAND_PREVIOUS_SIGNATURE: &str = "a609e19a03c2fcc559e8dae14900aaefe517cb55c840f6e69bc8e4f66c8d18e8a609685d9917efbfb0c37f058c2de88f13d297c7e19e0ab24813079efe57a182554ff054c7638153f9b26a60e7111f71a0ff63d9571704905d3ca6df0b031747";
+    pub const TESTING_DRAND_SIGNATURE: &str = "82f5d3d2de4db19d40a6980e8aa37842a0e55d1df06bd68bddc8d60002e8e959eb9cfa368b3c1b77d18f02a54fe047b80f0989315f83b12a74fd8679c4f12aae86eaf6ab5690b34f1fddd50ee3cc6f6cdf59e95526d5a5d82aaa84fa6f181e42";
+
+    pub fn testing_drand_config() -> DrandConfig {
+        DrandConfig {
+            pubkey: HexBinary::from_hex(TESTING_DRAND_PUBKEY).unwrap(),
+            genesis_time: Timestamp::from_seconds(TESTING_DRAND_GENESIS_TIME),
+            period: TESTING_DRAND_PERIOD,
+            safety_margin: None,
+        }
+    }
+
+    pub fn testing_salt() -> HexBinary {
+        HexBinary::from(TESTING_SALT)
+    }
+
+    pub fn testing_seed_commitment() -> HexBinary {
+        seed_commitment(TESTING_SEED, TESTING_SALT)
+    }
+
+    // A native ticket lottery with a 5% house fee, drawn from the committed testing seed.
+    // Tests override the fields they exercise.
+    pub fn testing_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            ticket_cost: coin(TESTING_TICKET_COST, TESTING_NATIVE_DENOM),
+            lottery_duration: TESTING_DURATION,
+            draw_deadline: None,
+            refund_window: None,
+            bond: None,
+            keeper_bounty: None,
+            admin: TEST_ADMIN.to_string(),
+            house_fee: 500,
+            ticket_mode: None,
+            randomness: None,
+            seed_commitment: Some(testing_seed_commitment()),
+        }
+    }
+
+    // The R of an ed25519 signature made with `nonce`, which the operator commits to.
+    pub fn ed25519_nonce_commitment(nonce: [u8; 32]) -> HexBinary {
+        let r = Scalar::from_bytes_mod_order(nonce);
+        HexBinary::from((&r * &ED25519_BASEPOINT_TABLE).compress().as_bytes())
+    }
+
+    // An RFC 8032 signature, except that the nonce is given rather than derived from the
+    // message, as an operator signing with a committed nonce does.
+    pub fn ed25519_sign_with_nonce(secret: [u8; 32], nonce: [u8; 32], message: &[u8]) -> [u8; 64] {
+        let expanded = Sha512::digest(secret);
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&expanded[..32]);
+        key[0] &= 248;
+        key[31] &= 127;
+        key[31] |= 64;
+        let key = Scalar::from_bytes_mod_order(key);
+        let public = (&key * &ED25519_BASEPOINT_TABLE).compress();
+        let r = Scalar::from_bytes_mod_order(nonce);
+        let big_r = ed25519_nonce_commitment(nonce);
+        let mut challenge = [0u8; 64];
+        challenge.copy_from_slice(
+            &Sha512::new()
+                .chain_update(big_r.as_slice())
+                .chain_update(public.as_bytes())
+                .chain_update(message)
+                .finalize(),
+        );
+        let s = r + Scalar::from_bytes_mod_order_wide(&challenge) * key;
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(big_r.as_slice());
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
 
     pub struct TestUser {
         pub addr: String,
@@ -1,7 +1,14 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Api, Coin, Decimal, HexBinary, Timestamp, Uint128};
+use drand_verify::{derive_randomness, G1Pubkey, Pubkey};
+use sha2::{Digest, Sha256};
 
-use crate::constants::MAX_HOUSE_FEE;
-use crate::state::Config;
+use crate::constants::{
+    DEFAULT_DRAND_SAFETY_MARGIN, DRAW_VERSION, MAX_HOUSE_FEE, MAX_KEEPER_BOUNTY_PERCENT,
+};
+use crate::state::{
+    Config, DrandConfig, KeeperBounty, OperatorSignatureConfig, OracleConfig, RandomnessSource,
+    SignatureScheme,
+};
 use crate::ContractError;
 use crate::ContractError::Unauthorized;
 
@@ -20,3 +27,323 @@ pub fn validate_house_fee(house_fee: u64) -> Result<u64, ContractError> {
         Ok(house_fee)
     }
 }
+
+pub fn validate_bond(
+    bond: Option<Coin>,
+    ticket_cost: &Coin,
+) -> Result<Option<Coin>, ContractError> {
+    match bond {
+        Some(bond) if bond.denom != ticket_cost.denom || bond.amount.is_zero() => {
+            Err(ContractError::InvalidBond {})
+        }
+        bond => Ok(bond),
+    }
+}
+
+pub fn validate_keeper_bounty(
+    keeper_bounty: Option<KeeperBounty>,
+) -> Result<Option<KeeperBounty>, ContractError> {
+    match keeper_bounty {
+        Some(KeeperBounty::PoolShare(share))
+            if share.is_zero() || share > Decimal::percent(MAX_KEEPER_BOUNTY_PERCENT) =>
+        {
+            Err(ContractError::InvalidKeeperBounty {})
+        }
+        Some(KeeperBounty::Fixed(amount)) if amount.is_zero() => {
+            Err(ContractError::InvalidKeeperBounty {})
+        }
+        keeper_bounty => Ok(keeper_bounty),
+    }
+}
+
+// A refunded player's share of an amount split over the round's tickets, e.g. a slashed bond.
+// Shares are taken from the cumulative count of refunded tickets, so they add up to the whole
+// amount whatever order players claim in.
+pub fn ticket_share(
+    amount: Uint128,
+    refunded_tickets: u64,
+    tickets: u64,
+    total_tickets: u64,
+) -> Uint128 {
+    let share_until = |tickets: u64| amount.multiply_ratio(tickets, total_tickets);
+    share_until(refunded_tickets + tickets) - share_until(refunded_tickets)
+}
+
+// Splits a prize pool into the house fee, the winner's prize and the dust. Both shares are
+// rounded down, the dust they leave stays in the contract and goes to the next round's pool.
+pub fn split_prize_pool(prize_pool: Uint128, house_fee: Decimal) -> (Uint128, Uint128, Uint128) {
+    let hundred = Uint128::from(100u128);
+    let fee = prize_pool * house_fee / hundred;
+    let winner_amount = prize_pool * (Decimal::percent(10_000) - house_fee) / hundred;
+    (fee, winner_amount, prize_pool - fee - winner_amount)
+}
+
+pub fn validate_seed_commitment(seed_commitment: HexBinary) -> Result<HexBinary, ContractError> {
+    if seed_commitment.len() != 32 {
+        Err(ContractError::InvalidSeedCommitment {})
+    } else {
+        Ok(seed_commitment)
+    }
+}
+
+// Commit-reveal rounds commit to the seed, operator signature rounds to the first 32 bytes
+// of the operator's signature (R for ed25519, r for secp256k1). Other sources don't use one.
+pub fn round_seed_commitment(
+    config: &Config,
+    seed_commitment: Option<HexBinary>,
+) -> Result<Option<HexBinary>, ContractError> {
+    match (&config.randomness, seed_commitment) {
+        (RandomnessSource::CommitReveal {}, Some(seed_commitment)) => {
+            Ok(Some(validate_seed_commitment(seed_commitment)?))
+        }
+        (RandomnessSource::OperatorSignature(_), Some(seed_commitment)) => {
+            Ok(Some(validate_seed_commitment(seed_commitment)?))
+        }
+        (RandomnessSource::CommitReveal {}, None)
+        | (RandomnessSource::OperatorSignature(_), None) => {
+            Err(ContractError::MissingSeedCommitment {})
+        }
+        _ => Ok(None),
+    }
+}
+
+// The commitment is sha256 over the big endian seed bytes followed by the salt.
+pub fn seed_commitment(seed: u64, salt: &[u8]) -> HexBinary {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    hasher.update(salt);
+    HexBinary::from(hasher.finalize().as_slice())
+}
+
+pub fn verify_seed_reveal(
+    seed_commitment_to_match: &HexBinary,
+    seed: u64,
+    salt: &[u8],
+) -> Result<(), ContractError> {
+    if seed_commitment(seed, salt) != *seed_commitment_to_match {
+        Err(ContractError::SeedCommitmentMismatch {})
+    } else {
+        Ok(())
+    }
+}
+
+// The draw entropy of an admin seed. The seed is hashed again, so that the draw can't be
+// computed from the published commitment before the reveal.
+pub fn seed_entropy(seed: u64, salt: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"draw");
+    hasher.update(seed.to_be_bytes());
+    hasher.update(salt);
+    hasher.finalize().into()
+}
+
+/*
+Draw v1, the winning ticket index is derived from the round's entropy with sha256:
+
+  digest(i) = sha256("sphinx-draw-v" || version as u32 BE || entropy || round as u64 BE
+                     || total_tickets as u64 BE || i as u32 BE)
+
+Starting at i = 0, the first 8 bytes of digest(i) are read as a big endian u64. The value is
+accepted if it's below the largest multiple of total_tickets that fits in a u64, and the winning
+index is the value modulo total_tickets. Otherwise i is incremented. Rejecting the remainder keeps
+every index equally likely, and the derivation can be reproduced off-chain from the stored inputs.
+*/
+pub fn winning_ticket(entropy: &[u8], round: u64, total_tickets: u64) -> Option<u64> {
+    if total_tickets == 0 {
+        return None;
+    }
+    let limit = u64::MAX - u64::MAX % total_tickets;
+    (0..=u32::MAX).find_map(|i| {
+        let mut hasher = Sha256::new();
+        hasher.update(b"sphinx-draw-v");
+        hasher.update(DRAW_VERSION.to_be_bytes());
+        hasher.update(entropy);
+        hasher.update(round.to_be_bytes());
+        hasher.update(total_tickets.to_be_bytes());
+        hasher.update(i.to_be_bytes());
+        let digest = hasher.finalize();
+
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&digest[..8]);
+        let value = u64::from_be_bytes(value);
+        (value < limit).then_some(value % total_tickets)
+    })
+}
+
+// Chains a buyer's contribution onto the round's accumulator, which starts out as 32 zero bytes.
+pub fn accumulate_entropy(
+    accumulator: Option<&[u8]>,
+    contributor: &Addr,
+    entropy: &[u8],
+) -> HexBinary {
+    let mut hasher = Sha256::new();
+    hasher.update(accumulator.unwrap_or(&[0u8; 32]));
+    hasher.update(contributor.as_bytes());
+    hasher.update(entropy);
+    HexBinary::from(hasher.finalize().as_slice())
+}
+
+// The draw entropy when buyers contributed: sha256(source entropy || participant accumulator).
+// Neither the randomness source nor the buyers control the result on their own.
+pub fn mix_entropy(source_entropy: &[u8; 32], participant_entropy: Option<&[u8]>) -> [u8; 32] {
+    match participant_entropy {
+        Some(participant_entropy) => {
+            let mut hasher = Sha256::new();
+            hasher.update(source_entropy);
+            hasher.update(participant_entropy);
+            hasher.finalize().into()
+        }
+        None => *source_entropy,
+    }
+}
+
+pub fn validate_randomness_source(
+    api: &dyn Api,
+    randomness: RandomnessSource,
+) -> Result<RandomnessSource, ContractError> {
+    match randomness {
+        RandomnessSource::Oracle(oracle) => Ok(RandomnessSource::Oracle(OracleConfig {
+            contract: api.addr_validate(oracle.contract.as_str())?,
+            fee: oracle.fee,
+        })),
+        RandomnessSource::Drand(drand) => {
+            Ok(RandomnessSource::Drand(validate_drand_config(drand)?))
+        }
+        // A zero timeout would have every request packet time out in the block it's sent.
+        RandomnessSource::Ibc(ibc) if ibc.timeout_seconds == 0 => {
+            Err(ContractError::InvalidIbcTimeout {})
+        }
+        RandomnessSource::OperatorSignature(operator) => Ok(RandomnessSource::OperatorSignature(
+            validate_operator_signature_config(operator)?,
+        )),
+        randomness => Ok(randomness),
+    }
+}
+
+// Every round asks the oracle for randomness under its own job id.
+pub fn oracle_job_id(round: u64) -> String {
+    format!("lottery-round-{}", round)
+}
+
+pub fn validate_operator_signature_config(
+    operator: OperatorSignatureConfig,
+) -> Result<OperatorSignatureConfig, ContractError> {
+    let valid = match operator.scheme {
+        SignatureScheme::Secp256k1 {} => matches!(operator.pubkey.len(), 33 | 65),
+        SignatureScheme::Ed25519 {} => operator.pubkey.len() == 32,
+    };
+    if valid {
+        Ok(operator)
+    } else {
+        Err(ContractError::InvalidOperatorPubkey {})
+    }
+}
+
+// Chains a purchase (no sender) or a transfer of tickets onto the round's ledger hash,
+// which starts out as 32 zero bytes. Addresses are length prefixed to keep the encoding unambiguous.
+pub fn ledger_hash(
+    previous: Option<&[u8]>,
+    sender: Option<&Addr>,
+    owner: &Addr,
+    tickets: u64,
+) -> HexBinary {
+    let mut hasher = Sha256::new();
+    hasher.update(previous.unwrap_or(&[0u8; 32]));
+    for addr in [sender.map(Addr::as_str).unwrap_or_default(), owner.as_str()] {
+        hasher.update((addr.len() as u32).to_be_bytes());
+        hasher.update(addr.as_bytes());
+    }
+    hasher.update(tickets.to_be_bytes());
+    HexBinary::from(hasher.finalize().as_slice())
+}
+
+// What the operator signs for a round:
+// sha256("sphinx-operator" || contract address || round as u64 BE || ticket ledger hash)
+pub fn operator_message(contract: &Addr, round: u64, ledger_hash: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"sphinx-operator");
+    hasher.update(contract.as_bytes());
+    hasher.update(round.to_be_bytes());
+    hasher.update(ledger_hash);
+    hasher.finalize().into()
+}
+
+// Half the order of the secp256k1 group, the largest s of a low-S signature.
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+// Verifies the operator's signature over the round's message and returns the entropy
+// derived from it, sha256(signature).
+// The operator committed to the signature's nonce when the round started, before the message
+// was known, so they can't grind nonces for a winner they like. With the nonce fixed there is
+// one valid signature left, as long as its encoding is canonical: secp256k1_verify also
+// accepts (r, n - s), so only low-S signatures are taken, and ed25519_verify already rejects
+// a non-canonical s.
+pub fn verify_operator_signature(
+    api: &dyn Api,
+    operator: &OperatorSignatureConfig,
+    nonce_commitment: &[u8],
+    message: &[u8; 32],
+    signature: &[u8],
+) -> Result<[u8; 32], ContractError> {
+    if signature.get(..32) != Some(nonce_commitment) {
+        return Err(ContractError::OperatorNonceMismatch {});
+    }
+    let verified = match operator.scheme {
+        SignatureScheme::Secp256k1 {} => {
+            if signature.len() != 64 || signature[32..] > SECP256K1_HALF_ORDER[..] {
+                return Err(ContractError::InvalidOperatorSignature {});
+            }
+            api.secp256k1_verify(message, signature, &operator.pubkey)
+        }
+        SignatureScheme::Ed25519 {} => api.ed25519_verify(message, signature, &operator.pubkey),
+    };
+    match verified {
+        Ok(true) => Ok(Sha256::digest(signature).into()),
+        _ => Err(ContractError::InvalidOperatorSignature {}),
+    }
+}
+
+pub fn validate_drand_config(drand: DrandConfig) -> Result<DrandConfig, ContractError> {
+    if drand.period == 0 || G1Pubkey::from_variable(&drand.pubkey).is_err() {
+        Err(ContractError::InvalidDrandConfig {})
+    } else {
+        Ok(DrandConfig {
+            safety_margin: Some(drand.safety_margin.unwrap_or(DEFAULT_DRAND_SAFETY_MARGIN)),
+            ..drand
+        })
+    }
+}
+
+// The first drand round published strictly after `time`, plus the safety margin. Nobody can
+// know its beacon yet when it gets fixed at close time, even with some clock drift or a block
+// time running behind.
+pub fn next_drand_round(drand: &DrandConfig, time: Timestamp) -> u64 {
+    // drand round 1 is published at genesis, round n at genesis + (n - 1) * period.
+    let elapsed = time.seconds().saturating_sub(drand.genesis_time.seconds());
+    elapsed / drand.period + 2 + drand.safety_margin.unwrap_or(DEFAULT_DRAND_SAFETY_MARGIN)
+}
+
+// When a drand round's beacon is published.
+pub fn drand_round_time(drand: &DrandConfig, round: u64) -> Timestamp {
+    drand
+        .genesis_time
+        .plus_seconds(round.saturating_sub(1) * drand.period)
+}
+
+// Verifies the beacon of a chained drand round and returns the randomness derived from it.
+pub fn verify_drand_beacon(
+    drand: &DrandConfig,
+    round: u64,
+    previous_signature: &[u8],
+    signature: &[u8],
+) -> Result<[u8; 32], ContractError> {
+    let pubkey =
+        G1Pubkey::from_variable(&drand.pubkey).map_err(|_| ContractError::InvalidDrandConfig {})?;
+    match pubkey.verify(round, previous_signature, signature) {
+        Ok(true) => Ok(derive_randomness(signature)),
+        _ => Err(ContractError::InvalidDrandBeacon { round }),
+    }
+}